@@ -0,0 +1,48 @@
+// -*- coding: utf-8 -*-
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright (C) 2025 - 2026 Michael Büsch <m@bues.ch>
+
+//! CRC-16 shared by the firmware and the host tools.
+//!
+//! CRC-16-CCITT (reflected, polynomial 0x8408).
+//! The initial value is 0xFFFF and there is no final XOR.
+
+#![no_std]
+#![forbid(unsafe_code)]
+
+/// Initial CRC value.
+pub const CRC16_INIT: u16 = 0xFFFF;
+
+/// CRC-16-CCITT (reflected, polynomial 0x8408) update.
+pub fn crc16_update(mut crc: u16, data: u8) -> u16 {
+    crc ^= data as u16;
+    for _ in 0..8 {
+        if crc & 1 != 0 {
+            crc = (crc >> 1) ^ 0x8408;
+        } else {
+            crc >>= 1;
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crc16(data: &[u8]) -> u16 {
+        data.iter().fold(CRC16_INIT, |crc, &b| crc16_update(crc, b))
+    }
+
+    #[test]
+    fn test_known_answer() {
+        // CRC-16/MCRF4XX check value.
+        // That is CRC-16/X-25 without the final XOR.
+        assert_eq!(crc16(b"123456789"), 0x6F91);
+        assert_eq!(crc16(b"123456789"), !0x906E);
+        assert_eq!(crc16(b""), 0xFFFF);
+        assert_eq!(crc16(&[0x00]), 0x0F87);
+    }
+}
+
+// vim: ts=4 sw=4 expandtab
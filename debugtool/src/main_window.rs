@@ -25,9 +25,16 @@ const MON_DEBOUNCE_FACT: f64 = N_MAX / 150.0;
 const TEMP_FACT: f64 = N_MAX / 100.0;
 const MAXRT_FACT: f64 = N_MAX / 0.010;
 const MINSTACK_FACT: f64 = N_MAX / 512.0;
+const SPEEDO_EDGES_FACT: f64 = N_MAX / 256.0;
+const MON_FAULT_FACT: f64 = N_MAX / 32.0;
+const EXT_ENABLE_FACT: f64 = N_MAX / 32.0;
 
 const STROKE_WIDTH: u32 = 3;
 
+/// Operating mode flags. Must match the firmware.
+const OP_MODE_OPEN_LOOP: u8 = 1 << 0;
+const OP_MODE_BURST: u8 = 1 << 1;
+
 struct DiagramVisibility {
     speedo: bool,
     speedo_status: bool,
@@ -38,6 +45,9 @@ struct DiagramVisibility {
     temp_uc: bool,
     maxrt: bool,
     minstack: bool,
+    speedo_edges: bool,
+    mon_fault: bool,
+    ext_enable: bool,
 }
 
 impl DiagramVisibility {
@@ -52,6 +62,9 @@ impl DiagramVisibility {
             temp_uc: false,
             maxrt: false,
             minstack: false,
+            speedo_edges: false,
+            mon_fault: false,
+            ext_enable: false,
         }
     }
 }
@@ -67,6 +80,12 @@ struct DiagramData {
     temp_uc: VecDeque<(f64, f64)>,
     maxrt: VecDeque<(f64, f64)>,
     minstack: VecDeque<(f64, f64)>,
+    speedo_rejected: VecDeque<(f64, f64)>,
+    speedo_missed: VecDeque<(f64, f64)>,
+    mon_fault: VecDeque<(f64, f64)>,
+    ext_enabled: VecDeque<(f64, f64)>,
+    ext_enable_deb: VecDeque<(f64, f64)>,
+    op_mode: Option<u8>,
     visibility: DiagramVisibility,
     run: bool,
 }
@@ -94,6 +113,12 @@ impl DiagramData {
             temp_uc: VecDeque::new(),
             maxrt: VecDeque::new(),
             minstack: VecDeque::new(),
+            speedo_rejected: VecDeque::new(),
+            speedo_missed: VecDeque::new(),
+            mon_fault: VecDeque::new(),
+            ext_enabled: VecDeque::new(),
+            ext_enable_deb: VecDeque::new(),
+            op_mode: None,
             visibility: DiagramVisibility::new(),
             run: true,
         }
@@ -110,6 +135,11 @@ impl DiagramData {
         oldest = check_ts!(oldest, self.temp_uc.front(), min);
         oldest = check_ts!(oldest, self.maxrt.front(), min);
         oldest = check_ts!(oldest, self.minstack.front(), min);
+        oldest = check_ts!(oldest, self.speedo_rejected.front(), min);
+        oldest = check_ts!(oldest, self.speedo_missed.front(), min);
+        oldest = check_ts!(oldest, self.mon_fault.front(), min);
+        oldest = check_ts!(oldest, self.ext_enabled.front(), min);
+        oldest = check_ts!(oldest, self.ext_enable_deb.front(), min);
         if oldest < f64::MAX { oldest } else { 0.0 }
     }
 
@@ -124,6 +154,11 @@ impl DiagramData {
         newest = check_ts!(newest, self.temp_uc.back(), max);
         newest = check_ts!(newest, self.maxrt.back(), max);
         newest = check_ts!(newest, self.minstack.back(), max);
+        newest = check_ts!(newest, self.speedo_rejected.back(), max);
+        newest = check_ts!(newest, self.speedo_missed.back(), max);
+        newest = check_ts!(newest, self.mon_fault.back(), max);
+        newest = check_ts!(newest, self.ext_enabled.back(), max);
+        newest = check_ts!(newest, self.ext_enable_deb.back(), max);
         newest
     }
 
@@ -176,6 +211,24 @@ impl DiagramData {
                 self.minstack
                     .push_back((self.timestamp(t), val as f64 * MINSTACK_FACT));
             }
+            SerDat::SpeedoEdges(t, rejected, missed) => {
+                self.speedo_rejected
+                    .push_back((self.timestamp(t), rejected as f64 * SPEEDO_EDGES_FACT));
+                self.speedo_missed
+                    .push_back((self.timestamp(t), missed as f64 * SPEEDO_EDGES_FACT));
+            }
+            SerDat::MonFault(t, val) => {
+                self.mon_fault
+                    .push_back((self.timestamp(t), val as f64 * MON_FAULT_FACT));
+            }
+            SerDat::ExtEnable(t, enabled, count) => {
+                self.ext_enabled
+                    .push_back((self.timestamp(t), enabled as f64 * N_MAX / 2.0));
+                self.ext_enable_deb
+                    .push_back((self.timestamp(t), count as f64 * EXT_ENABLE_FACT));
+            }
+            SerDat::OpMode(_, val) => self.op_mode = Some(val),
+            SerDat::Profile(..) => (),
             SerDat::Sync => (),
         }
         Self::prune_items(&mut self.speedo, age_thres);
@@ -187,6 +240,28 @@ impl DiagramData {
         Self::prune_items(&mut self.temp_uc, age_thres);
         Self::prune_items(&mut self.maxrt, age_thres);
         Self::prune_items(&mut self.minstack, age_thres);
+        Self::prune_items(&mut self.speedo_rejected, age_thres);
+        Self::prune_items(&mut self.speedo_missed, age_thres);
+        Self::prune_items(&mut self.mon_fault, age_thres);
+        Self::prune_items(&mut self.ext_enabled, age_thres);
+        Self::prune_items(&mut self.ext_enable_deb, age_thres);
+    }
+
+    fn mode_text(&self) -> String {
+        let Some(mode) = self.op_mode else {
+            return "mode: unknown".to_string();
+        };
+        let control = if mode & OP_MODE_OPEN_LOOP != 0 {
+            "OPEN-LOOP (no speed control)"
+        } else {
+            "closed-loop"
+        };
+        let triac = if mode & OP_MODE_BURST != 0 {
+            "full-wave burst"
+        } else {
+            "phase angle"
+        };
+        format!("mode: {control}\ntriac: {triac}")
     }
 }
 
@@ -358,6 +433,80 @@ fn draw(backend: CairoBackend, diagram_data: Rc<RefCell<DiagramData>>) {
             });
     }
 
+    if diagram_data.visibility.speedo_edges {
+        chart
+            .draw_series(LineSeries::new(
+                diagram_data.speedo_rejected.iter().copied(),
+                full_palette::PURPLE.stroke_width(STROKE_WIDTH),
+            ))
+            .unwrap()
+            .label("speedo-rejected")
+            .legend(|(x, y)| {
+                PathElement::new(
+                    vec![(x, y), (x + 20, y)],
+                    full_palette::PURPLE.stroke_width(STROKE_WIDTH),
+                )
+            });
+        chart
+            .draw_series(LineSeries::new(
+                diagram_data.speedo_missed.iter().copied(),
+                full_palette::PINK.stroke_width(STROKE_WIDTH),
+            ))
+            .unwrap()
+            .label("speedo-missed")
+            .legend(|(x, y)| {
+                PathElement::new(
+                    vec![(x, y), (x + 20, y)],
+                    full_palette::PINK.stroke_width(STROKE_WIDTH),
+                )
+            });
+    }
+
+    if diagram_data.visibility.mon_fault {
+        chart
+            .draw_series(LineSeries::new(
+                diagram_data.mon_fault.iter().copied(),
+                full_palette::RED_900.stroke_width(STROKE_WIDTH),
+            ))
+            .unwrap()
+            .label("mon-fault")
+            .legend(|(x, y)| {
+                PathElement::new(
+                    vec![(x, y), (x + 20, y)],
+                    full_palette::RED_900.stroke_width(STROKE_WIDTH),
+                )
+            });
+    }
+
+    if diagram_data.visibility.ext_enable {
+        chart
+            .draw_series(LineSeries::new(
+                diagram_data.ext_enabled.iter().copied(),
+                full_palette::LIGHTBLUE.stroke_width(STROKE_WIDTH),
+            ))
+            .unwrap()
+            .label("ext-enable")
+            .legend(|(x, y)| {
+                PathElement::new(
+                    vec![(x, y), (x + 20, y)],
+                    full_palette::LIGHTBLUE.stroke_width(STROKE_WIDTH),
+                )
+            });
+        chart
+            .draw_series(LineSeries::new(
+                diagram_data.ext_enable_deb.iter().copied(),
+                full_palette::INDIGO.stroke_width(STROKE_WIDTH),
+            ))
+            .unwrap()
+            .label("ext-enable-debounce")
+            .legend(|(x, y)| {
+                PathElement::new(
+                    vec![(x, y), (x + 20, y)],
+                    full_palette::INDIGO.stroke_width(STROKE_WIDTH),
+                )
+            });
+    }
+
     chart
         .configure_series_labels()
         .margin(15)
@@ -372,6 +521,7 @@ fn periodic_work(
     ser_rx: Rc<mpsc::Receiver<SerDat>>,
     diagram_area: Rc<RefCell<DiagramArea>>,
     diagram_data: Rc<RefCell<DiagramData>>,
+    mode_label: &gtk::Label,
 ) {
     let mut diagram_data = diagram_data.borrow_mut();
     for dat in ser_rx.try_iter() {
@@ -379,6 +529,7 @@ fn periodic_work(
             diagram_data.add(dat);
         }
     }
+    mode_label.set_text(&diagram_data.mode_text());
     drop(diagram_data);
     diagram_area.borrow().redraw();
 }
@@ -444,8 +595,13 @@ impl MainWindow {
         connect_signal_cb!(builder, "cb_temp_uc", temp_uc);
         connect_signal_cb!(builder, "cb_maxrt", maxrt);
         connect_signal_cb!(builder, "cb_minstack", minstack);
+        connect_signal_cb!(builder, "cb_speedo_edges", speedo_edges);
+        connect_signal_cb!(builder, "cb_mon_fault", mon_fault);
+        connect_signal_cb!(builder, "cb_ext_enable", ext_enable);
         connect_run_cb!(builder, "cb_run");
 
+        let mode_label: gtk::Label = builder.object("lbl_mode").expect("Label not found");
+
         glib::source::timeout_add_local(Duration::from_millis(100), {
             let diagram_area = Rc::clone(&diagram_area);
             let diagram_data = Rc::clone(&diagram_data);
@@ -454,6 +610,7 @@ impl MainWindow {
                     Rc::clone(&ser_rx),
                     Rc::clone(&diagram_area),
                     Rc::clone(&diagram_data),
+                    &mode_label,
                 );
                 glib::ControlFlow::Continue
             }
@@ -24,6 +24,11 @@ pub enum SerDat {
     TempUc(Instant, f64),
     MaxRt(Instant, f64),
     MinStack(Instant, u16),
+    SpeedoEdges(Instant, u8, u8),
+    MonFault(Instant, u16),
+    ExtEnable(Instant, bool, u8),
+    Profile(Instant, u8, u8),
+    OpMode(Instant, u8),
     Sync,
 }
 
@@ -72,19 +77,62 @@ impl SerDat {
             6 => Ok(SerDat::TempUc(now, fixpt_to_celsius(val))),
             7 => Ok(SerDat::MaxRt(now, raw_to_reltime(val))),
             8 => Ok(SerDat::MinStack(now, val)),
+            9 => Ok(SerDat::SpeedoEdges(now, buf[1], buf[2])),
+            10 => Ok(SerDat::MonFault(now, val)),
+            11 => Ok(SerDat::ExtEnable(now, buf[2] != 0, buf[1])),
+            12 => Ok(SerDat::Profile(now, buf[2], buf[1])),
+            13 => Ok(SerDat::OpMode(now, buf[1])),
             0xFF => Ok(SerDat::Sync),
             cmd => Err(err!("SerBuf::parse: Unknown command 0x{cmd:02X}")),
         }
     }
 }
 
+/// Assembles the calibration profile name and hash, which are sent byte by byte.
+#[derive(Default)]
+struct ProfileRx {
+    bytes: Vec<u8>,
+    reported: Option<(String, u16)>,
+}
+
+impl ProfileRx {
+    fn handle(&mut self, dat: &SerDat) {
+        let SerDat::Profile(_, index, data) = *dat else {
+            return;
+        };
+        if index as usize != self.bytes.len() {
+            // Lost a byte. Restart at the next hash byte.
+            self.bytes.clear();
+            if index != 0 {
+                return;
+            }
+        }
+        if index >= 2 && data == 0 {
+            let hash = u16::from_le_bytes([self.bytes[0], self.bytes[1]]);
+            let name = String::from_utf8_lossy(&self.bytes[2..]).into_owned();
+            let profile = Some((name, hash));
+            if profile != self.reported {
+                if let Some((name, hash)) = &profile {
+                    println!("Calibration profile: {name} (hash 0x{hash:04X})");
+                }
+                self.reported = profile;
+            }
+            self.bytes.clear();
+        } else {
+            self.bytes.push(data);
+        }
+    }
+}
+
 fn process_one(
     serial: &mut Box<dyn serialport::SerialPort>,
     notify_tx: &mpsc::Sender<SerDat>,
+    profile: &mut ProfileRx,
 ) -> ah::Result<()> {
     let mut buf: SerBuf = Default::default();
     serial.read_exact(&mut buf).context("Serial port read")?;
     let dat = SerDat::parse(&buf).context("Parse SerBuf")?;
+    profile.handle(&dat);
     notify_tx.send(dat).context("Send SerDat")?;
     Ok(())
 }
@@ -122,9 +170,10 @@ pub fn run_serial(port: &str, notify_tx: &mpsc::Sender<SerDat>) -> ah::Result<()
 
     // Main serial communication loop.
     let mut debounce = 0_usize;
+    let mut profile = ProfileRx::default();
     synchronize(&mut serial)?;
     loop {
-        match process_one(&mut serial, notify_tx) {
+        match process_one(&mut serial, notify_tx, &mut profile) {
             Ok(_) => {
                 debounce = debounce.saturating_sub(1);
             }
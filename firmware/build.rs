@@ -0,0 +1,31 @@
+// -*- coding: utf-8 -*-
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright (C) 2025 - 2026 Michael Büsch <m@bues.ch>
+
+//! Generate the calibration profile module from `profiles/$RPMCONTROL_PROFILE.toml`.
+//!
+//! The profile is validated by `profilegen` and the build fails, if it is not plausible.
+
+#![forbid(unsafe_code)]
+
+use anyhow::{self as ah, Context as _};
+use std::{env, fs, path::PathBuf};
+
+/// Profile used, if `RPMCONTROL_PROFILE` is not set.
+const DEFAULT_PROFILE: &str = "default";
+
+fn main() -> ah::Result<()> {
+    println!("cargo::rerun-if-env-changed=RPMCONTROL_PROFILE");
+    println!("cargo::rerun-if-changed=profiles");
+
+    let name = env::var("RPMCONTROL_PROFILE").unwrap_or_else(|_| DEFAULT_PROFILE.to_string());
+    let path = PathBuf::from("profiles").join(format!("{name}.toml"));
+    let text = fs::read_to_string(&path).context(format!("Read {}", path.display()))?;
+    let code = profilegen::generate(&name, &text).context(format!("Profile {}", path.display()))?;
+
+    let out = PathBuf::from(env::var("OUT_DIR").context("OUT_DIR")?).join("profile.rs");
+    fs::write(&out, code).context(format!("Write {}", out.display()))?;
+    Ok(())
+}
+
+// vim: ts=4 sw=4 expandtab
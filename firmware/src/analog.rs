@@ -18,8 +18,15 @@ pub enum AdcChannel {
     Setpoint,
     MotTemp,
     UcTemp,
+    /// Test channel: Internal 1.1 V bandgap.
+    Bandgap,
+    /// Test channel: GND.
+    Gnd,
 }
 
+/// Number of ADC channels.
+const NR_CHANNELS: usize = 5;
+
 impl AdcChannel {
     pub const fn mask(&self) -> u8 {
         1 << *self as usize
@@ -29,7 +36,9 @@ impl AdcChannel {
         match self {
             Self::Setpoint => Self::MotTemp,
             Self::MotTemp => Self::UcTemp,
-            Self::UcTemp => Self::Setpoint,
+            Self::UcTemp => Self::Bandgap,
+            Self::Bandgap => Self::Gnd,
+            Self::Gnd => Self::Setpoint,
         }
     }
 }
@@ -38,7 +47,7 @@ pub struct Adc {
     chan: MainCtxCell<AdcChannel>,
     settled: MainCtxCell<bool>,
     running: MainCtxCell<bool>,
-    result: [MainCtxCell<u16>; 3],
+    result: [MainCtxCell<u16>; NR_CHANNELS],
     ok: MainCtxCell<u8>,
 }
 
@@ -84,6 +93,24 @@ impl Adc {
                      .mux().set(0x1F)
                 });
             }
+            AdcChannel::Bandgap => {
+                ADC.adcsrb().modify(|_, w| {
+                    w.mux5().clear_bit()
+                });
+                ADC.admux().write(|w| {
+                    w.refs().vcc()
+                     .mux().set(0x1E)
+                });
+            }
+            AdcChannel::Gnd => {
+                ADC.adcsrb().modify(|_, w| {
+                    w.mux5().clear_bit()
+                });
+                ADC.admux().write(|w| {
+                    w.refs().vcc()
+                     .mux().set(0x1F)
+                });
+            }
         }
         self.set_settled(m, false);
     }
@@ -106,6 +133,7 @@ impl Adc {
 
     #[allow(non_snake_case)]
     #[rustfmt::skip]
+    #[inline(never)]
     pub fn init(&self, m: &MainCtx<'_>, ADC: &mcu::ADC) {
         setup_didr(ADC);
 
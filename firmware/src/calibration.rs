@@ -3,6 +3,8 @@
 // Copyright (C) 2025 - 2026 Michael Büsch <m@bues.ch>
 
 //! Calibration constants and tables.
+//!
+//! The machine variant specific values are taken from the build-time calibration profile.
 
 use crate::{
     freq::Freq,
@@ -15,6 +17,14 @@ use crate::{
 use avr_q::{Q7p8, Q15p8, q7p8, q15p8};
 use curveipo::Curve;
 
+/// Build-time calibration profile.
+/// Generated by `build.rs` from `profiles/$RPMCONTROL_PROFILE.toml`.
+pub mod profile {
+    use super::*;
+
+    include!(concat!(env!("OUT_DIR"), "/profile.rs"));
+}
+
 /// Basic system parameters.
 pub mod system {
     use super::*;
@@ -23,26 +33,40 @@ pub mod system {
     pub const STARTUP_DELAY: RelLargeTimestamp = RelLargeTimestamp::from_millis(300);
 
     /// Nominal maximum motor RPM.
-    pub const MAX_RPM: i16 = 24000;
+    pub const MAX_RPM: i16 = profile::MAX_RPM;
 
     /// Maximum motor RPM that will trigger a hard triac inhibit.
-    pub const MOT_SOFT_LIMIT: Freq = rpm!(MAX_RPM + 500);
+    pub const MOT_SOFT_LIMIT: Freq = profile::MOT_SOFT_LIMIT;
 
     /// Maximum motor RPM that will trigger a monitoring fault.
-    pub const MOT_HARD_LIMIT: Freq = rpm!(MAX_RPM + 1500);
+    pub const MOT_HARD_LIMIT: Freq = profile::MOT_HARD_LIMIT;
+
+    /// Number of main loop iterations over which `WDT_WINDOW_MIN` is checked.
+    /// One iteration can be shorter than one timer tick.
+    pub const WDT_WINDOW_LOOPS: u8 = 16;
+
+    /// Minimum time for `WDT_WINDOW_LOOPS` watchdog services.
+    /// The system is reset, if the main loop iterations are faster than this.
+    ///
+    /// The shortest path through the compiled main loop
+    /// (startup state without optional features) takes at least 171 CPU cycles.
+    /// That is 10.7 us at 16 MHz and 171 us for 16 iterations.
+    /// One timer tick is subtracted for the timestamp resolution
+    /// and one tick as margin.
+    pub const WDT_WINDOW_MIN: RelLargeTimestamp = RelLargeTimestamp::from_micros(171 - 2 * 16);
+
+    /// Maximum time between two watchdog services.
+    /// The system is reset, if a main loop iteration is slower than this.
+    /// Must be smaller than the hardware watchdog timeout.
+    pub const WDT_WINDOW_MAX: RelLargeTimestamp = RelLargeTimestamp::from_millis(10);
 }
 
 /// RPM PID controller parameters.
 pub mod rpm_pid {
-    use super::system::MAX_RPM;
     use super::*;
 
     /// RPM PID parameters for normal operation.
-    pub const RPMPID_PARAMS: PidParams = PidParams {
-        kp: q7p8!(const 8 / 5),
-        ki: q7p8!(const 3 / 32),
-        kd: q7p8!(const 1 / 80),
-    };
+    pub const RPMPID_PARAMS: PidParams = profile::RPMPID_PARAMS;
 
     /// RPM PID parameters for speedometer syncing.
     pub const RPMPID_PARAMS_SYNCING: PidParams = PidParams {
@@ -52,22 +76,12 @@ pub mod rpm_pid {
     };
 
     /// Negative I-limit curve for the RPM PID controller.
-    pub const RPMPID_ILIM_NEG: Curve<Q7p8, (Q7p8, Q7p8), 4> = Curve::new([
-        // (speedo, I-limit)
-        (rpm!(0).0, q7p8!(const 0)),
-        (rpm!(1000).0, q7p8!(const 0)),
-        (rpm!(1001).0, q7p8!(const -10)),
-        (rpm!(MAX_RPM).0, q7p8!(const -10)),
-    ]);
+    /// (speedo, I-limit)
+    pub const RPMPID_ILIM_NEG: Curve<Q7p8, (Q7p8, Q7p8), 4> = Curve::new(profile::RPMPID_ILIM_NEG);
 
     /// Positive I-limit curve for the RPM PID controller.
-    pub const RPMPID_ILIM_POS: Curve<Q7p8, (Q7p8, Q7p8), 4> = Curve::new([
-        // (speedo, I-limit)
-        (rpm!(0).0, q7p8!(const 0)),
-        (rpm!(1000).0, q7p8!(const 0)),
-        (rpm!(1001).0, q7p8!(const 80)),
-        (rpm!(MAX_RPM).0, q7p8!(const 80)),
-    ]);
+    /// (speedo, I-limit)
+    pub const RPMPID_ILIM_POS: Curve<Q7p8, (Q7p8, Q7p8), 4> = Curve::new(profile::RPMPID_ILIM_POS);
 }
 
 /// Setpoint measurement and processing.
@@ -82,7 +96,32 @@ pub mod setpoint {
     pub const SP_STEPS: i16 = 100;
 
     /// Setpoint below this threshold will enforce speedometer re-syncing.
-    pub const SP_SYNC_THRES: Freq = rpm!(1000);
+    /// The window measurement is valid down to lower speeds.
+    pub const SP_SYNC_THRES: Freq = if super::speedo::SPEEDO_WINDOW_MODE {
+        rpm!(350)
+    } else {
+        rpm!(1000)
+    };
+}
+
+/// External enable input (door switch or emergency stop contact).
+/// The enable contact on PA2 is selected with the feature `ext-enable`.
+pub mod ext_enable {
+    use super::*;
+
+    /// Sampling step of the enable input.
+    #[cfg_attr(not(feature = "ext-enable"), allow(dead_code))]
+    pub const EXT_ENABLE_STEP: RelLargeTimestamp = RelLargeTimestamp::from_millis(2);
+
+    /// Step size for one sample of an open contact.
+    pub const EXT_ENABLE_DEBOUNCE_ERRSTEP: u8 = 5;
+    /// Debounce limit to disable the machine.
+    /// An open contact disables the machine after two samples.
+    pub const EXT_ENABLE_DEBOUNCE_LIMIT: u8 = 10;
+    /// Number of consecutive closed samples before a disabled machine is re-enabled.
+    /// Re-enabling is debounced longer than disabling.
+    /// A stable closed contact enables the machine after 10 to 25 samples.
+    pub const EXT_ENABLE_RELEASE_COUNT: u8 = 10;
 }
 
 /// Speedometer measurement and filtering.
@@ -100,7 +139,26 @@ pub mod speedo {
 
     /// Low level timeout.
     /// If no speedometer edge is detected for this long, reset the speedometer Ok count.
-    pub const SPEEDO_LOWLEVEL_TIMEOUT: RelLargeTimestamp = RelLargeTimestamp::from_millis(50);
+    /// In window mode this must be longer than the edge distance at the lowest measurable speed.
+    pub const SPEEDO_LOWLEVEL_TIMEOUT: RelLargeTimestamp = if SPEEDO_WINDOW_MODE {
+        RelLargeTimestamp::from_millis(80)
+    } else {
+        RelLargeTimestamp::from_millis(50)
+    };
+
+    /// Low speed measurement by counting edges over a time window.
+    /// The window mode is selected with the feature `speedo-window`.
+    pub const SPEEDO_WINDOW_MODE: bool = cfg!(feature = "speedo-window");
+
+    /// Low speed measurement window.
+    /// Below `WINDOW_MODE_THRES` the speed is measured by counting edges over this time window.
+    pub const SPEEDO_WINDOW: RelLargeTimestamp = RelLargeTimestamp::from_millis(100);
+
+    /// Switch from period measurement to window measurement below this speed.
+    pub const WINDOW_MODE_THRES: Freq = rpm!(1500);
+
+    /// Switch from window measurement to period measurement at or above this speed.
+    pub const PERIOD_MODE_THRES: Freq = rpm!(2000);
 
     /// Low level Ok counter threshold.
     /// Need at least this many valid speedometer edges in a row to consider the speed valid for the first time.
@@ -110,16 +168,43 @@ pub mod speedo {
     /// Filters the measured low level speedometer edge durations.
     pub const FILTER_SHIFT: u8 = 5;
 
+    /// Low level plausibility window lower bound.
+    /// Edge durations shorter than this, relative to the filtered duration, are rejected.
+    /// In units of 1/16 of the filtered duration.
+    pub const PLAUSIBLE_LO: i16 = 10;
+
+    /// Low level plausibility window upper bound.
+    /// Edge durations longer than this, relative to the filtered duration, are rejected.
+    /// In units of 1/16 of the filtered duration.
+    pub const PLAUSIBLE_HI: i16 = 26;
+
+    /// Low level missed edge window lower bound.
+    /// Edge durations within the missed edge window span two edges and are halved.
+    /// In units of 1/16 of the filtered duration.
+    pub const MISSED_EDGE_LO: i16 = 28;
+
+    /// Low level missed edge window upper bound.
+    /// In units of 1/16 of the filtered duration.
+    pub const MISSED_EDGE_HI: i16 = 36;
+
+    /// Low level rejection threshold.
+    /// After this many rejected edges in a row, the speedometer is re-synchronized.
+    pub const REJECT_RESYNC_THRES: u8 = 4;
+
     /// Physical layout.
     /// Number of speedometer edges per motor revolution.
-    pub const SPEEDO_FACT: u32 = 4;
+    pub const SPEEDO_FACT: u32 = profile::SPEEDO_FACT;
 
     /// Substitute speedometer value curve during syncing when the actual speedometer input is invalid.
-    pub const SYNC_SPEEDO_SUBSTITUTE: Curve<Freq, (Freq, Freq), 2> = Curve::new([
+    pub const SYNC_SPEEDO_SUBSTITUTE: Curve<Freq, (Freq, Freq), 2> =
+        Curve::new(SYNC_SPEEDO_SUBSTITUTE_POINTS);
+
+    /// See `SYNC_SPEEDO_SUBSTITUTE`.
+    pub const SYNC_SPEEDO_SUBSTITUTE_POINTS: [(Freq, Freq); 2] = [
         // (setpoint, speedo-substitute)
         (rpm!(0), rpm!(0)),
         (rpm!(1000), rpm!(800)),
-    ]);
+    ];
 }
 
 /// Mains zero crossing detection.
@@ -141,25 +226,18 @@ pub mod temp {
     use super::*;
 
     /// High temperature limit for the motor, above which a shutoff will be triggered.
-    pub const TEMP_LIMIT_HI: Q7p8 = celsius!(100);
+    pub const TEMP_LIMIT_HI: Q7p8 = profile::TEMP_LIMIT_HI;
 
     /// Low temperature limit for the motor, below which a shutoff will be released.
-    pub const TEMP_LIMIT_LO: Q7p8 = celsius!(80);
+    pub const TEMP_LIMIT_LO: Q7p8 = profile::TEMP_LIMIT_LO;
 
     /// Temperature filter divider.
     pub const TEMP_FILTER_DIV: Q15p8 = q15p8!(const 16);
 
     /// Motor NTC temperature curve.
-    pub const NTC_CURVE: Curve<Q7p8, (Q7p8, Q7p8), 7> = Curve::new([
-        // (kOhms, double deg Celsius)
-        (q7p8!(const 3321 / 10000), celsius!(145)),
-        (q7p8!(const 5174 / 10000), celsius!(125)),
-        (q7p8!(const 8400 / 10000), celsius!(105)),
-        (q7p8!(const 1429 / 1000), celsius!(85)),
-        (q7p8!(const 2565 / 1000), celsius!(65)),
-        (q7p8!(const 4891 / 1000), celsius!(45)),
-        (q7p8!(const 1000 / 100), celsius!(25)),
-    ]);
+    /// (kOhms, double deg Celsius)
+    pub const NTC_CURVE: Curve<Q7p8, (Q7p8, Q7p8), { profile::NTC_CURVE_LEN }> =
+        Curve::new(profile::NTC_CURVE);
 
     /// Maximum kOhms for the motor NTC, above which a shutoff will immediately be triggered.
     pub const TEMP_MOT_KOHMS_LIM_HI: Q7p8 = q7p8!(const 120);
@@ -168,16 +246,20 @@ pub mod temp {
     pub const TEMP_MOT_KOHMS_LIM_LO: Q7p8 = q7p8!(const 1 / 10);
 
     /// Microcontroller temperature curve.
-    pub const UC_CURVE: Curve<Q7p8, (Q7p8, Q7p8), 3> = Curve::new([
+    pub const UC_CURVE: Curve<Q7p8, (Q7p8, Q7p8), 3> = Curve::new(UC_CURVE_POINTS);
+
+    /// See `UC_CURVE`.
+    pub const UC_CURVE_POINTS: [(Q7p8, Q7p8); 3] = [
         // (adc / 8, double deg Celsius)
         (q7p8!(const 300 / 8), celsius!(25)),
         (q7p8!(const 370 / 8), celsius!(85)),
         (q7p8!(const 440 / 8), celsius!(145)),
-    ]);
+    ];
 }
 
 /// Monitoring constants and tables.
 pub mod mon {
+    use super::system::MAX_RPM;
     use super::*;
 
     /// Distance between monitoring checks.
@@ -196,9 +278,22 @@ pub mod mon {
     /// Immediate fault, if the main loop execution time is bigger than this.
     pub const MAX_MAIN_RT_LIMIT: RelLargeTimestamp = RelLargeTimestamp::from_millis(5);
 
+    /// Minimum ADC result of the 1.1 V bandgap test channel.
+    /// Bandgap 1.0 V at VCC 5.5 V.
+    pub const ADC_BANDGAP_MIN: u16 = 186;
+
+    /// Maximum ADC result of the 1.1 V bandgap test channel.
+    /// Bandgap 1.2 V at VCC 4.5 V.
+    pub const ADC_BANDGAP_MAX: u16 = 273;
+
+    /// Maximum ADC result of the GND test channel.
+    pub const ADC_GND_MAX: u16 = 8;
+
     /// Setpoint and speedometer history buffer.
     /// Distance between history buffer elements.
     /// Length = HIST_DIST * HIST_COUNT = 3 seconds
+    /// The length is never a single timestamp, but each distance must be below
+    /// half of the `LargeTimestamp` range for the wrapping timestamp comparison.
     pub const HIST_DIST: RelLargeTimestamp = RelLargeTimestamp::from_micros(333333);
     /// Number of elements in the history buffer.
     pub const HIST_COUNT: usize = 9;
@@ -230,6 +325,35 @@ pub mod mon {
     /// So the monitoring timeout for the speedometer is roughly
     /// `NO_SPEED_TIMEOUT` * `MON_NO_SPEED_TIMEOUT_COUNT_THRES`.
     pub const MON_NO_SPEED_TIMEOUT_COUNT_THRES: u8 = 20;
+
+    /// Locked rotor detection.
+    /// The motor is considered to be stalled, if the RPM controller output is at or above this.
+    pub const STALL_PIDY_THRES: Freq = rpm!(MAX_RPM / 4 * 3);
+
+    /// Locked rotor detection.
+    /// The motor is considered to be stalled, if the speed is below this absolute limit...
+    pub const STALL_SPEED_THRES: Freq = rpm!(200);
+
+    /// ... or if the speed is below this fraction of the setpoint.
+    pub const STALL_SPEED_FRAC: Q7p8 = q7p8!(const 1 / 4);
+
+    /// Locked rotor detection.
+    /// If the motor is stalled for this number of `CHECK_DIST` times, then a hard monitoring fault is triggered.
+    pub const STALL_TIMEOUT_COUNT: u8 = 75;
+
+    /// Shorted triac detection.
+    /// While the primary shutoff is active, the speed is checked every
+    /// `SHUTOFF_DECAY_COUNT` * `CHECK_DIST`.
+    pub const SHUTOFF_DECAY_COUNT: u8 = 50;
+
+    /// Shorted triac detection.
+    /// Minimum speed decay between two shutoff checks.
+    /// If the speed decays less than this, then a hard monitoring fault is triggered.
+    pub const SHUTOFF_DECAY_MIN: Freq = rpm!(200);
+
+    /// Shorted triac detection.
+    /// Speeds below this are considered to be fully decayed.
+    pub const SHUTOFF_SPEED_ZERO: Freq = rpm!(500);
 }
 
 /// Monitoring Power-On-Check constants and tables.
@@ -252,4 +376,140 @@ pub mod mon_pocheck {
     pub const DEBUG_PIN_ENA: bool = true;
 }
 
+/// Status LED blink codes.
+/// The status LED on PB6 (active high) is selected with the feature `status-led`.
+/// It disables the power-on-check debug pin toggling.
+pub mod status_led {
+    use super::*;
+
+    /// Duration of one step of the blink pattern.
+    #[cfg_attr(not(feature = "status-led"), allow(dead_code))]
+    pub const STATUS_LED_STEP: RelLargeTimestamp = RelLargeTimestamp::from_millis(200);
+
+    /// Number of steps with LED off at the end of each blink pattern.
+    #[cfg_attr(not(feature = "status-led"), allow(dead_code))]
+    pub const STATUS_LED_PAUSE: u8 = 5;
+}
+
+/// Open-loop (manual phase angle) mode.
+pub mod open_loop {
+    use super::system::MAX_RPM;
+    use super::*;
+
+    /// The open-loop mode is selected with the feature `open-loop`.
+    pub const OPEN_LOOP: bool = cfg!(feature = "open-loop");
+
+    /// Controller output curve in open-loop mode.
+    /// The controller output is converted to the firing angle in the same way as in closed-loop mode.
+    pub const OPEN_LOOP_CURVE: Curve<Freq, (Freq, Freq), 3> = Curve::new(OPEN_LOOP_CURVE_POINTS);
+
+    /// See `OPEN_LOOP_CURVE`.
+    pub const OPEN_LOOP_CURVE_POINTS: [(Freq, Freq); 3] = [
+        // (setpoint, controller output)
+        (rpm!(0), rpm!(0)),
+        (rpm!(MAX_RPM / 4), rpm!(MAX_RPM / 2)),
+        (rpm!(MAX_RPM), rpm!(MAX_RPM / 5 * 4)),
+    ];
+
+    /// Conduction angle limit in open-loop mode.
+    /// Maximum controller output. A higher output is a monitoring fault.
+    pub const OPEN_LOOP_Y_MAX: Freq = rpm!(MAX_RPM / 5 * 4);
+}
+
+/// Compile-time checks of the calibration invariants.
+/// A violation fails the build.
+mod checks {
+    use super::{
+        ext_enable::*, mon::*, open_loop::*, setpoint::*, speedo::*, system::*, temp::*, *,
+    };
+    use crate::timer::TIMER_TICK_US;
+
+    /// Check that the curve X coordinates are strictly increasing.
+    const fn x_increasing(points: &[(Q7p8, Q7p8)]) -> bool {
+        let mut i = 1;
+        while i < points.len() {
+            if points[i].0.to_q() <= points[i - 1].0.to_q() {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    /// Check that the `Freq` curve X coordinates are strictly increasing.
+    const fn freq_x_increasing(points: &[(Freq, Freq)]) -> bool {
+        let mut i = 1;
+        while i < points.len() {
+            if points[i].0.0.to_q() <= points[i - 1].0.0.to_q() {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    // Motor speed limits.
+    const _: () = assert!(rpm!(MAX_RPM).0.to_q() < MOT_SOFT_LIMIT.0.to_q());
+    const _: () = assert!(MOT_SOFT_LIMIT.0.to_q() < MOT_HARD_LIMIT.0.to_q());
+
+    // Temperature limits with hysteresis.
+    const _: () = assert!(TEMP_LIMIT_LO.to_q() < TEMP_LIMIT_HI.to_q());
+    const _: () = assert!(TEMP_MOT_KOHMS_LIM_LO.to_q() < TEMP_MOT_KOHMS_LIM_HI.to_q());
+
+    // Setpoint thresholds.
+    const _: () = assert!(SP_MIN_CUTOFF.0.to_q() < SP_SYNC_THRES.0.to_q());
+    const _: () = assert!(SP_SYNC_THRES.0.to_q() < MON_ACTIVE_THRES.0.to_q());
+    const _: () = assert!(SP_STEPS > 0);
+
+    // Speedometer timeouts, windows and mode switching hysteresis.
+    const _: () = assert!(NO_SPEED_TIMEOUT.0 > SPEEDO_LOWLEVEL_TIMEOUT.0);
+    const _: () = assert!(WINDOW_MODE_THRES.0.to_q() < PERIOD_MODE_THRES.0.to_q());
+    const _: () = assert!(PLAUSIBLE_LO < 16 && 16 < PLAUSIBLE_HI);
+    const _: () = assert!(PLAUSIBLE_HI <= MISSED_EDGE_LO && MISSED_EDGE_LO < MISSED_EDGE_HI);
+
+    // Watchdog window. The lower bound must be resolvable by the timer.
+    const _: () = assert!(WDT_WINDOW_MIN.0 > 1);
+    const _: () = assert!(WDT_WINDOW_MIN.0 < WDT_WINDOW_MAX.0);
+
+    // Monitoring history. The gradient needs two elements.
+    const _: () = assert!(HIST_COUNT >= 2);
+    // The history update compares the next sample time with the current time.
+    // The distance plus the longest main loop runtime must be below
+    // half of the `LargeTimestamp` range for the wrapping comparison.
+    const _: () = assert!(HIST_DIST.0 > 0);
+    const _: () = assert!(HIST_DIST.0 as i32 + MAX_MAIN_RT_LIMIT.0 as i32 <= i16::MAX as i32);
+    // Length = HIST_DIST * HIST_COUNT = 3 seconds, with the timer resolution.
+    // This is longer than the `LargeTimestamp` range and never used as one timestamp.
+    const _: () = {
+        let len_us = HIST_DIST.to_micros() as i64 * HIST_COUNT as i64;
+        let tolerance = (TIMER_TICK_US as i64) * HIST_COUNT as i64;
+        assert!(len_us <= 3_000_000 && len_us > 3_000_000 - tolerance);
+    };
+    const _: () = assert!(CHECK_DIST.0 < CHECK_TIMEOUT.0);
+
+    // External enable debounce.
+    const _: () = assert!(EXT_ENABLE_DEBOUNCE_ERRSTEP > 0);
+    const _: () = assert!(EXT_ENABLE_DEBOUNCE_LIMIT as u16 * 2 <= u8::MAX as u16);
+    const _: () = assert!(
+        EXT_ENABLE_RELEASE_COUNT >= EXT_ENABLE_DEBOUNCE_LIMIT.div_ceil(EXT_ENABLE_DEBOUNCE_ERRSTEP)
+    );
+
+    // Curves.
+    const _: () = assert!(x_increasing(&profile::NTC_CURVE));
+    const _: () = assert!(x_increasing(&UC_CURVE_POINTS));
+    const _: () = assert!(x_increasing(&profile::RPMPID_ILIM_NEG));
+    const _: () = assert!(x_increasing(&profile::RPMPID_ILIM_POS));
+    const _: () = assert!(freq_x_increasing(&SYNC_SPEEDO_SUBSTITUTE_POINTS));
+    const _: () = assert!(freq_x_increasing(&OPEN_LOOP_CURVE_POINTS));
+
+    // The open-loop curve must stay within the conduction angle limit.
+    const _: () = {
+        let mut i = 0;
+        while i < OPEN_LOOP_CURVE_POINTS.len() {
+            assert!(OPEN_LOOP_CURVE_POINTS[i].1.0.to_q() <= OPEN_LOOP_Y_MAX.0.to_q());
+            i += 1;
+        }
+    };
+}
+
 // vim: ts=4 sw=4 expandtab
@@ -19,6 +19,7 @@ impl<const ERRSTEP: u8, const LIMIT: u8, const STICKY: bool> Debounce<ERRSTEP, L
         self.count.get(m) < LIMIT
     }
 
+    #[inline(never)]
     pub fn error(&self, m: &MainCtx<'_>) {
         self.count.set(m, self.count.get(m).saturating_add(ERRSTEP));
     }
@@ -27,6 +28,7 @@ impl<const ERRSTEP: u8, const LIMIT: u8, const STICKY: bool> Debounce<ERRSTEP, L
         self.count.set(m, LIMIT);
     }
 
+    #[inline(never)]
     pub fn ok(&self, m: &MainCtx<'_>) {
         if !STICKY || self.is_ok(m) {
             self.count.set(m, self.count.get(m).saturating_sub(1));
@@ -3,7 +3,7 @@
 // Copyright (C) 2025 - 2026 Michael Büsch <m@bues.ch>
 
 use crate::timer::RelLargeTimestamp;
-use avr_context::{InitCtx, IrqCtx};
+use avr_context::{InitCtx, IrqCtx, MainCtx};
 use avr_q::Q7p8;
 
 #[cfg_attr(not(feature = "debug"), allow(dead_code))]
@@ -19,9 +19,27 @@ pub enum Debug {
     TempUc,
     MaxRt,
     MinStack,
+    SpeedoEdges,
+    MonFault,
+    #[cfg_attr(not(feature = "ext-enable"), allow(dead_code))]
+    ExtEnable,
+    Profile,
+    OpMode,
 }
 #[cfg_attr(not(feature = "debug"), allow(dead_code))]
-const NRVALUES: usize = 9;
+const NRVALUES: usize = 14;
+
+/// Returns true, if a complete set of values has been sent since the previous call.
+#[cfg_attr(not(feature = "debug"), allow(unused_variables))]
+pub fn run(m: &MainCtx<'_>) -> bool {
+    #[cfg(feature = "debug")]
+    let ret = inner::run(m);
+
+    #[cfg(not(feature = "debug"))]
+    let ret = false;
+
+    ret
+}
 
 #[cfg_attr(not(feature = "debug"), allow(dead_code))]
 #[cfg_attr(not(feature = "debug"), allow(unused_variables))]
@@ -39,6 +57,7 @@ pub fn tx_complete_callback(c: &IrqCtx) {
 
 impl Debug {
     #[cfg_attr(not(feature = "debug"), allow(unused_variables))]
+    #[cfg_attr(feature = "debug", inline(never))]
     pub fn log_u16(&self, value: u16) {
         #[cfg(feature = "debug")]
         inner::log_u16(*self as u16, value);
@@ -50,6 +69,7 @@ impl Debug {
     }
 
     #[cfg_attr(not(feature = "debug"), allow(dead_code))]
+    #[cfg_attr(feature = "debug", inline(never))]
     pub fn log_u8(&self, value: u8) {
         self.log_u16(value.into());
     }
@@ -81,21 +101,16 @@ mod inner {
     const INDEXSHIFT: usize = 2;
     const INDEXMASK: u8 = (1 << INDEXSHIFT) - 1;
 
-    static VALUES: Mutex<[Cell<u16>; NRVALUES]> = Mutex::new([
-        Cell::new(0),
-        Cell::new(0),
-        Cell::new(0),
-        Cell::new(0),
-        Cell::new(0),
-        Cell::new(0),
-        Cell::new(0),
-        Cell::new(0),
-        Cell::new(0),
-    ]);
+    static VALUES: Mutex<[Cell<u16>; NRVALUES]> = Mutex::new([const { Cell::new(0) }; NRVALUES]);
     static INDEX: Mutex<Cell<u8>> = Mutex::new(Cell::new(0));
+    static ROUND: Mutex<Cell<bool>> = Mutex::new(Cell::new(false));
 
     pub fn rx_complete_callback(_c: &IrqCtx, _data: u8) {}
 
+    pub fn run(_m: &MainCtx<'_>) -> bool {
+        with_cs(|cs| ROUND.borrow(cs).replace(false))
+    }
+
     #[allow(clippy::collapsible_match)]
     pub fn tx_complete_callback(c: &IrqCtx) {
         let cs = c.cs();
@@ -125,6 +140,7 @@ mod inner {
                 if uart_tx_cs(cs, (value >> 8) as u8) {
                     if id >= NRVALUES as u8 {
                         INDEX.borrow(cs).set(0);
+                        ROUND.borrow(cs).set(true);
                     } else {
                         INDEX.borrow(cs).set((id + 1) << INDEXSHIFT);
                     }
@@ -0,0 +1,118 @@
+// -*- coding: utf-8 -*-
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright (C) 2025 - 2026 Michael Büsch <m@bues.ch>
+
+//! External enable input (door switch or emergency stop contact) on PA2.
+//!
+//! The contact is closed to GND to enable the machine.
+//! An open contact or a broken wire disables the machine.
+
+use crate::shutoff::Shutoff;
+use avr_context::MainCtx;
+
+#[cfg(feature = "ext-enable")]
+use crate::{
+    calibration::ext_enable::{
+        EXT_ENABLE_DEBOUNCE_ERRSTEP, EXT_ENABLE_DEBOUNCE_LIMIT, EXT_ENABLE_RELEASE_COUNT,
+        EXT_ENABLE_STEP,
+    },
+    debounce::Debounce,
+    debug::Debug,
+    ports::{PORTA, PortOps as _},
+    timer::{LargeTimestamp, timer_get_large},
+};
+#[cfg(feature = "ext-enable")]
+use avr_context::{MainCtxCell, with_cs};
+
+/// PORTA bit of the enable input.
+#[cfg(feature = "ext-enable")]
+const PORTA_BIT: usize = 2;
+
+#[cfg(feature = "ext-enable")]
+pub struct ExtEnable {
+    deb: Debounce<EXT_ENABLE_DEBOUNCE_ERRSTEP, EXT_ENABLE_DEBOUNCE_LIMIT, false>,
+    next_sample: MainCtxCell<LargeTimestamp>,
+    closed_count: MainCtxCell<u8>,
+}
+
+#[cfg(feature = "ext-enable")]
+impl ExtEnable {
+    pub const fn new() -> Self {
+        Self {
+            deb: Debounce::new(),
+            next_sample: MainCtxCell::new(LargeTimestamp::new()),
+            closed_count: MainCtxCell::new(0),
+        }
+    }
+
+    pub fn init(&self, m: &MainCtx<'_>) {
+        // Start in disabled state.
+        self.deb.error_no_debounce(m);
+    }
+
+    /// Sample and debounce the enable input.
+    pub fn run(&self, m: &MainCtx<'_>) {
+        let now = timer_get_large();
+
+        if now >= self.next_sample.get(m) {
+            self.next_sample.set(m, now + EXT_ENABLE_STEP);
+
+            let closed = !with_cs(|cs| PORTA.get(cs, PORTA_BIT));
+            if closed {
+                let closed_count = self.closed_count.get(m).saturating_add(1);
+                self.closed_count.set(m, closed_count);
+                // A disabled machine is only re-enabled,
+                // if the contact is stable closed for the full release count.
+                if self.deb.is_ok(m) || closed_count >= EXT_ENABLE_RELEASE_COUNT {
+                    self.deb.ok(m);
+                }
+            } else {
+                self.closed_count.set(m, 0);
+                if self.deb.count(m) < EXT_ENABLE_DEBOUNCE_LIMIT * 2 {
+                    // Limit the count to bound the time to re-enable.
+                    self.deb.error(m);
+                }
+            }
+        }
+
+        Debug::ExtEnable.log_u16(((self.is_enabled(m) as u16) << 8) | self.deb.count(m) as u16);
+    }
+
+    /// Returns true, if the debounced enable contact is closed.
+    pub fn is_enabled(&self, m: &MainCtx<'_>) -> bool {
+        self.deb.is_ok(m)
+    }
+}
+
+/// No enable contact is connected. PA2 is not connected (DNC).
+/// The machine is always enabled.
+#[cfg(not(feature = "ext-enable"))]
+pub struct ExtEnable;
+
+#[cfg(not(feature = "ext-enable"))]
+impl ExtEnable {
+    pub const fn new() -> Self {
+        Self
+    }
+
+    pub fn init(&self, _m: &MainCtx<'_>) {}
+
+    pub fn run(&self, _m: &MainCtx<'_>) {}
+
+    pub fn is_enabled(&self, _m: &MainCtx<'_>) -> bool {
+        true
+    }
+}
+
+impl ExtEnable {
+    /// Get the shutoff state requested by the enable input.
+    pub fn get_shutoff(&self, m: &MainCtx<'_>) -> Shutoff {
+        if self.is_enabled(m) {
+            Shutoff::MachineRunning
+        } else {
+            Shutoff::MachineShutoff
+        }
+    }
+}
+
+// vim: ts=4 sw=4 expandtab
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 // Copyright (C) 2025 - 2026 Michael Büsch <m@bues.ch>
 
+use crate::history::History;
 use avr_context::{MainCtx, MainCtxCell};
 use avr_q::{Q7p8, Q15p8, q7p8, q15p8};
 
@@ -102,4 +103,36 @@ impl FilterI16 {
     }
 }
 
+/// A median filter for i16 values over the last 3 inputs.
+pub struct Median3I16 {
+    hist: History<i16, 3>,
+}
+
+impl Median3I16 {
+    /// Create a new filter with zero initial state.
+    pub const fn new() -> Self {
+        Self {
+            hist: History::new(MainCtxCell::new_array(0)),
+        }
+    }
+
+    /// Set all filter elements to the given value.
+    #[inline(never)]
+    pub fn set(&self, m: &MainCtx<'_>, value: i16) {
+        self.hist.fill(m, value);
+    }
+
+    /// Run the filter with the given input.
+    ///
+    /// Returns the median of the last 3 inputs.
+    pub fn run(&self, m: &MainCtx<'_>, input: i16) -> i16 {
+        self.hist.push_back(m, input);
+
+        let a = self.hist.get(m, 0);
+        let b = self.hist.get(m, 1);
+        let c = self.hist.get(m, 2);
+        a.min(b).max(a.max(b).min(c))
+    }
+}
+
 // vim: ts=4 sw=4 expandtab
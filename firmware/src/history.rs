@@ -22,6 +22,12 @@ impl<T: Copy, const SIZE: usize> History<T, SIZE> {
         self.hist[SIZE - 1].set(m, new);
     }
 
+    pub fn fill(&self, m: &MainCtx<'_>, value: T) {
+        for hist in &self.hist {
+            hist.set(m, value);
+        }
+    }
+
     pub fn get(&self, m: &MainCtx<'_>, index: usize) -> T {
         self.hist[index].get(m)
     }
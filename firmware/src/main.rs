@@ -12,6 +12,7 @@ mod calibration;
 mod debounce;
 mod debug;
 mod exint;
+mod extenable;
 mod filter;
 mod freq;
 mod history;
@@ -21,21 +22,25 @@ mod mon;
 mod mon_pocheck;
 mod pid;
 mod ports;
+mod profile;
 mod ring;
 mod shutoff;
 mod snap;
 mod speedo;
+mod statusled;
 mod system;
 mod temp;
 mod timer;
 mod triac;
 mod usi_uart;
+mod wdt;
 
-use crate::{hw::mcu, system::System};
+use crate::{hw::mcu, system::System, wdt::WdtWindow};
 use avr_context::{InitCtx, MainCtx, define_main};
-use avr_device::{asm::wdr, interrupt};
+use avr_device::interrupt;
 
 static SYSTEM: System = System::new();
+static WDT_WINDOW: WdtWindow = WdtWindow::new();
 
 /// Reset the system.
 #[inline(always)]
@@ -80,7 +85,7 @@ struct MainDp {
 fn main_loop(c: &MainCtx<'_>, dp: MainDp) -> ! {
     loop {
         SYSTEM.run(c, &dp.ADC);
-        wdr();
+        WDT_WINDOW.service(c);
     }
 }
 
@@ -92,7 +97,8 @@ fn init(c: &InitCtx<'_>, dp: InitDp) -> MainDp {
     usi_uart::setup(c);
     debug::setup(c);
 
-    SYSTEM.init(c.main_ctx(), &dp.ADC, &dp.AC);
+    SYSTEM.init(c, &dp.ADC, &dp.AC);
+    WDT_WINDOW.init(c.main_ctx());
 
     MainDp { ADC: dp.ADC }
 }
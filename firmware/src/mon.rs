@@ -5,11 +5,15 @@
 use crate::{
     calibration::{
         mon::{
-            ACCELERATION_GRADIENT_LO_THRES, CHECK_DIST, CHECK_TIMEOUT, ERROR_DEBOUNCE_ERRSTEP,
-            ERROR_DEBOUNCE_LIMIT, ERROR_DEBOUNCE_STICKY, HIST_COUNT, HIST_DIST,
-            MAINS_ZERO_CROSSING_TIMEOUT, MAX_MAIN_RT_LIMIT, MIN_STACK_SPACE, MON_ACTIVE_THRES,
-            MON_NO_SPEED_TIMEOUT_COUNT_THRES, SP_GRADIENT_THRES, SPEEDO_TOLERANCE,
+            ACCELERATION_GRADIENT_LO_THRES, ADC_BANDGAP_MAX, ADC_BANDGAP_MIN, ADC_GND_MAX,
+            CHECK_DIST, CHECK_TIMEOUT, ERROR_DEBOUNCE_ERRSTEP, ERROR_DEBOUNCE_LIMIT,
+            ERROR_DEBOUNCE_STICKY, HIST_COUNT, HIST_DIST, MAINS_ZERO_CROSSING_TIMEOUT,
+            MAX_MAIN_RT_LIMIT, MIN_STACK_SPACE, MON_ACTIVE_THRES, MON_NO_SPEED_TIMEOUT_COUNT_THRES,
+            SHUTOFF_DECAY_COUNT, SHUTOFF_DECAY_MIN, SHUTOFF_SPEED_ZERO, SP_GRADIENT_THRES,
+            SPEEDO_TOLERANCE, STALL_PIDY_THRES, STALL_SPEED_FRAC, STALL_SPEED_THRES,
+            STALL_TIMEOUT_COUNT,
         },
+        open_loop::OPEN_LOOP,
         speedo::NO_SPEED_TIMEOUT,
         system::MOT_HARD_LIMIT,
     },
@@ -17,7 +21,7 @@ use crate::{
     debug::Debug,
     freq::Freq,
     history::History,
-    shutoff::Shutoff,
+    shutoff::{Shutoff, secondary_shutoff_readback_ok},
     timer::{LargeTimestamp, RelLargeTimestamp, timer_get_large},
 };
 use avr_atomic::AvrAtomic;
@@ -27,6 +31,69 @@ use avr_stack::estimate_unused_stack_space;
 
 static ANALOG_FAILURE: AvrAtomic<bool> = AvrAtomic::new();
 
+/// Monitoring fault code.
+/// Only the first detected fault is latched.
+#[derive(Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MonFault {
+    /// No fault.
+    None = 0,
+    /// Speedometer deviates from the setpoint.
+    SpeedoDeviation,
+    /// Motor speed above `MOT_HARD_LIMIT`.
+    MotHardLimit,
+    /// Monitoring check distance too big.
+    MonCheckDist,
+    /// Not enough free stack space.
+    Stack,
+    /// Main loop runtime too long.
+    MaxMainRt,
+    /// Analog processing failure.
+    Analog,
+    /// Mains 90 degree distance too big.
+    Mains90degDist,
+    /// No valid speedometer for too long.
+    SpeedoOk,
+    /// Locked rotor. High conduction without speed.
+    Stall,
+    /// Speed does not decay during primary shutoff.
+    ShortedTriac,
+    /// Secondary shutoff path failure.
+    SecondaryShutoff,
+    /// ADC test channel out of tolerance.
+    Adc,
+    /// External enable input open. This fault is not latched.
+    ExtEnable,
+    /// Power-on-check failure.
+    PoCheck,
+    /// Conduction angle above the open-loop limit.
+    ConductionAngle,
+}
+
+/// Input values for the monitoring checks.
+pub struct MonInput {
+    /// RPM setpoint.
+    pub setpoint: Freq,
+    /// Filtered speedometer value.
+    pub speedo: Freq,
+    /// The raw speedometer signal is valid.
+    pub speedo_ok: bool,
+    /// RPM controller output.
+    pub pid_y: Freq,
+    /// The conduction commanded to the triac exceeds the open-loop limit.
+    pub conduction_high: bool,
+    /// State of the primary (triac) shutoff path.
+    pub primary_shutoff: Shutoff,
+    /// We are at mains zero crossing + 90 degrees.
+    pub mains_90deg: bool,
+    /// ADC result of the bandgap test channel.
+    pub adc_bandgap: Option<u16>,
+    /// ADC result of the GND test channel.
+    pub adc_gnd: Option<u16>,
+    /// State requested by the external enable input.
+    pub ext_enable: Shutoff,
+}
+
 /// RPM controller state for monitoring.
 #[derive(Copy, Clone)]
 struct MonControllerState {
@@ -43,6 +110,51 @@ struct MonHardFailures {
     stack_failure: bool,
     max_main_rt_failure: bool,
     analog_failure: bool,
+    stall_failure: bool,
+    shorted_triac_failure: bool,
+    secondary_readback_failure: bool,
+    adc_failure: bool,
+    conduction_failure: bool,
+}
+
+impl MonHardFailures {
+    /// Get the fault code of the first hard failure, if any.
+    fn fault(&self) -> Option<MonFault> {
+        if self.stack_failure {
+            return Some(MonFault::Stack);
+        }
+        if self.mon_check_dist_failure {
+            return Some(MonFault::MonCheckDist);
+        }
+        if self.analog_failure {
+            return Some(MonFault::Analog);
+        }
+        if self.mains_90deg_dist_failure {
+            return Some(MonFault::Mains90degDist);
+        }
+        if self.speedo_ok_failure {
+            return Some(MonFault::SpeedoOk);
+        }
+        if self.max_main_rt_failure {
+            return Some(MonFault::MaxMainRt);
+        }
+        if self.stall_failure {
+            return Some(MonFault::Stall);
+        }
+        if self.shorted_triac_failure {
+            return Some(MonFault::ShortedTriac);
+        }
+        if self.secondary_readback_failure {
+            return Some(MonFault::SecondaryShutoff);
+        }
+        if self.adc_failure {
+            return Some(MonFault::Adc);
+        }
+        if self.conduction_failure {
+            return Some(MonFault::ConductionAngle);
+        }
+        None
+    }
 }
 
 /// Monitoring system.
@@ -52,6 +164,12 @@ pub struct Mon {
     prev_speedo: MainCtxCell<LargeTimestamp>,
     prev_hist: MainCtxCell<LargeTimestamp>,
     no_speed_count: MainCtxCell<u8>,
+    stall_count: MainCtxCell<u8>,
+    shutoff_count: MainCtxCell<u8>,
+    shutoff_ref: MainCtxCell<Freq>,
+    prev_primary_shutoff: MainCtxCell<Shutoff>,
+    fault: MainCtxCell<MonFault>,
+    ext_shutoff: MainCtxCell<bool>,
     error_deb: Debounce<ERROR_DEBOUNCE_ERRSTEP, ERROR_DEBOUNCE_LIMIT, ERROR_DEBOUNCE_STICKY>,
     hist: History<MonControllerState, HIST_COUNT>,
     prev_main_rt_stamp: MainCtxCell<LargeTimestamp>,
@@ -67,6 +185,12 @@ impl Mon {
             prev_speedo: MainCtxCell::new(LargeTimestamp::new()),
             prev_hist: MainCtxCell::new(LargeTimestamp::new()),
             no_speed_count: MainCtxCell::new(0),
+            stall_count: MainCtxCell::new(0),
+            shutoff_count: MainCtxCell::new(0),
+            shutoff_ref: MainCtxCell::new(Freq(q7p8!(const 0))),
+            prev_primary_shutoff: MainCtxCell::new(Shutoff::MachineShutoff),
+            fault: MainCtxCell::new(MonFault::None),
+            ext_shutoff: MainCtxCell::new(false),
             error_deb: Debounce::new(),
             hist: History::new(MainCtxCell::new_array(MonControllerState {
                 setpoint: Freq(q7p8!(const 0)),
@@ -118,6 +242,7 @@ impl Mon {
     }
 
     /// Check the distance between mains zero crossings.
+    #[inline(never)]
     fn mon_check_mains_90deg(
         &self,
         m: &MainCtx<'_>,
@@ -167,6 +292,96 @@ impl Mon {
         self.no_speed_count.set(m, count);
     }
 
+    /// Check for a locked rotor.
+    fn mon_check_stall(
+        &self,
+        m: &MainCtx<'_>,
+        main_checks_now: bool,
+        ctrl_state: &MonControllerState,
+        pid_y: Freq,
+        hard_failures: &mut MonHardFailures,
+    ) {
+        let mut count = self.stall_count.get(m);
+
+        // The motor is stalled, if the controller demands high conduction,
+        // but the speed is zero or collapsed far below the setpoint.
+        let speed_thres = Freq(ctrl_state.setpoint.0 * STALL_SPEED_FRAC).max(STALL_SPEED_THRES);
+        let stalled = pid_y >= STALL_PIDY_THRES && ctrl_state.speedo < speed_thres;
+
+        if !stalled {
+            count = 0;
+        } else if main_checks_now {
+            count = count.saturating_add(1);
+        }
+
+        // If the motor was stalled for too long,
+        // then we have a hard failure.
+        hard_failures.stall_failure = count >= STALL_TIMEOUT_COUNT;
+
+        self.stall_count.set(m, count);
+    }
+
+    /// Check that the motor speed decays while the primary shutoff path is active.
+    /// A shorted triac would keep the motor running.
+    fn mon_check_primary_shutoff(
+        &self,
+        m: &MainCtx<'_>,
+        main_checks_now: bool,
+        ctrl_state: &MonControllerState,
+        primary_shutoff: Shutoff,
+        hard_failures: &mut MonHardFailures,
+    ) {
+        let prev_primary_shutoff = self.prev_primary_shutoff.get(m);
+        self.prev_primary_shutoff.set(m, primary_shutoff);
+
+        if primary_shutoff == Shutoff::MachineRunning {
+            // The triac is allowed to run. Nothing to check.
+            return;
+        }
+        if prev_primary_shutoff == Shutoff::MachineRunning {
+            // Primary shutoff just started. Remember the reference speed.
+            self.shutoff_ref.set(m, ctrl_state.speedo);
+            self.shutoff_count.set(m, 0);
+            return;
+        }
+        if !main_checks_now {
+            return;
+        }
+
+        let count = self.shutoff_count.get(m).saturating_add(1);
+        if count < SHUTOFF_DECAY_COUNT {
+            self.shutoff_count.set(m, count);
+            return;
+        }
+        self.shutoff_count.set(m, 0);
+
+        // The speed must have decayed since the last reference,
+        // unless the motor is already (almost) stopped.
+        let speed = ctrl_state.speedo;
+        let decayed =
+            speed <= SHUTOFF_SPEED_ZERO || speed <= self.shutoff_ref.get(m) - SHUTOFF_DECAY_MIN;
+        if !decayed && cfg!(feature = "monitoring") {
+            hard_failures.shorted_triac_failure = true;
+        }
+        self.shutoff_ref.set(m, speed);
+    }
+
+    /// Check the conduction angle limit in open-loop mode.
+    /// The conduction angle is the only motor quantity that is known without a speedometer.
+    fn mon_check_conduction(
+        &self,
+        _m: &MainCtx<'_>,
+        conduction_high: bool,
+        hard_failures: &mut MonHardFailures,
+    ) {
+        hard_failures.conduction_failure = conduction_high;
+    }
+
+    /// Check the secondary shutoff path pin readback.
+    fn mon_check_secondary_readback(&self, _m: &MainCtx<'_>, hard_failures: &mut MonHardFailures) {
+        hard_failures.secondary_readback_failure = !secondary_shutoff_readback_ok();
+    }
+
     /// Check the CPU stack usage.
     fn mon_check_stack_usage(&self, _m: &MainCtx<'_>, hard_failures: &mut MonHardFailures) {
         let unused_stack_bytes = estimate_unused_stack_space();
@@ -189,13 +404,39 @@ impl Mon {
         hard_failures.analog_failure = ANALOG_FAILURE.load();
     }
 
+    /// Check the ADC test channels.
+    /// A drifting VCC reference or a broken ADC shows up here.
+    fn mon_check_adc(
+        &self,
+        _m: &MainCtx<'_>,
+        input: &MonInput,
+        hard_failures: &mut MonHardFailures,
+    ) {
+        if let Some(bandgap) = input.adc_bandgap
+            && !(ADC_BANDGAP_MIN..=ADC_BANDGAP_MAX).contains(&bandgap)
+        {
+            hard_failures.adc_failure = true;
+        }
+        if let Some(gnd) = input.adc_gnd
+            && gnd > ADC_GND_MAX
+        {
+            hard_failures.adc_failure = true;
+        }
+    }
+
     /// Do the main periodic monitoring checks.
+    #[inline(never)]
     fn mon_main_checks(&self, m: &MainCtx<'_>, ctrl_state: &MonControllerState) {
         // If the motor speed is above the hard limit, then we have a major problem.
         if ctrl_state.speedo >= MOT_HARD_LIMIT && cfg!(feature = "monitoring") {
             // We already know that we have an error.
             // Do not run the remaining checks.
             self.error_deb.error(m);
+            // Only latch the fault code, if the debounce has tripped.
+            // A short spike must not mask the real fault.
+            if !self.error_deb.is_ok(m) {
+                self.latch_fault(m, MonFault::MotHardLimit);
+            }
             return;
         }
         // The motor speed is not above the hard limit or the hard limit check is disabled.
@@ -251,16 +492,12 @@ impl Mon {
     }
 
     /// Do the monitoring checks and return the shutoff state.
-    pub fn check(
-        &self,
-        m: &MainCtx<'_>,
-        setpoint: Freq,
-        speedo: Freq,
-        speedo_ok: bool,
-        mains_90deg: bool,
-    ) -> Shutoff {
+    pub fn check(&self, m: &MainCtx<'_>, input: &MonInput) -> Shutoff {
         let mut hard_failures = MonHardFailures::default();
-        let ctrl_state = MonControllerState { setpoint, speedo };
+        let ctrl_state = MonControllerState {
+            setpoint: input.setpoint,
+            speedo: input.speedo,
+        };
         let now = timer_get_large();
 
         // Update monitoring history.
@@ -269,40 +506,90 @@ impl Mon {
         // Monitoring distance.
         let main_checks_now = self.mon_distance_check(m, now, &mut hard_failures);
 
-        // Check if we need to do the main monitoring checks now.
-        if main_checks_now {
-            self.mon_main_checks(m, &ctrl_state);
+        if OPEN_LOOP {
+            // There is no speed measurement in open-loop mode.
+            // Only the conduction angle can be checked.
+            self.mon_check_conduction(m, input.conduction_high, &mut hard_failures);
+        } else {
+            // Check if we need to do the main monitoring checks now.
+            if main_checks_now {
+                self.mon_main_checks(m, &ctrl_state);
+            }
+            self.mon_check_speedo_ok(m, now, &ctrl_state, input.speedo_ok, &mut hard_failures);
+            self.mon_check_stall(
+                m,
+                main_checks_now,
+                &ctrl_state,
+                input.pid_y,
+                &mut hard_failures,
+            );
+            self.mon_check_primary_shutoff(
+                m,
+                main_checks_now,
+                &ctrl_state,
+                input.primary_shutoff,
+                &mut hard_failures,
+            );
         }
 
         // Run the remaining hard failure checks.
-        self.mon_check_mains_90deg(m, now, mains_90deg, &mut hard_failures);
-        self.mon_check_speedo_ok(m, now, &ctrl_state, speedo_ok, &mut hard_failures);
+        self.mon_check_mains_90deg(m, now, input.mains_90deg, &mut hard_failures);
+        self.mon_check_secondary_readback(m, &mut hard_failures);
         self.mon_check_stack_usage(m, &mut hard_failures);
         self.mon_check_main_runtime(m, &mut hard_failures);
         self.mon_check_analog_failure(m, &mut hard_failures);
+        self.mon_check_adc(m, input, &mut hard_failures);
+
+        // The external enable input is not a hard failure.
+        // It is only reported as fault code.
+        self.ext_shutoff
+            .set(m, input.ext_enable == Shutoff::MachineShutoff);
 
         // Do we have any hard failure?
-        if hard_failures.stack_failure
-            || hard_failures.mon_check_dist_failure
-            || hard_failures.analog_failure
-            || hard_failures.mains_90deg_dist_failure
-            || hard_failures.speedo_ok_failure
-            || hard_failures.max_main_rt_failure
-        {
+        if let Some(fault) = hard_failures.fault() {
             // We have a hard failure.
             // Raise an immediate and permanent error without debouncing.
+            self.latch_fault(m, fault);
             self.error_deb.error_no_debounce(m);
         }
 
         Debug::MonDebounce.log_u8(self.error_deb.count(m));
+        Debug::MonFault.log_u8(self.get_fault(m) as u8);
 
         if self.error_deb.is_ok(m) {
             Shutoff::MachineRunning
         } else {
+            // The debounced speedometer check failed, if no other fault has been latched.
+            self.latch_fault(m, MonFault::SpeedoDeviation);
             Shutoff::MachineShutoff
         }
     }
 
+    /// Report a fault that has been detected outside of the monitoring system.
+    /// This raises an immediate and permanent error.
+    pub fn report_fault(&self, m: &MainCtx<'_>, fault: MonFault) {
+        self.latch_fault(m, fault);
+        self.error_deb.error_no_debounce(m);
+    }
+
+    /// Latch the fault code, if no other fault has been latched before.
+    #[inline(never)]
+    fn latch_fault(&self, m: &MainCtx<'_>, fault: MonFault) {
+        if self.fault.get(m) == MonFault::None {
+            self.fault.set(m, fault);
+        }
+        Debug::MonFault.log_u8(self.fault.get(m) as u8);
+    }
+
+    /// Get the latched fault code.
+    /// If no fault is latched, an open external enable input is reported.
+    pub fn get_fault(&self, m: &MainCtx<'_>) -> MonFault {
+        match self.fault.get(m) {
+            MonFault::None if self.ext_shutoff.get(m) => MonFault::ExtEnable,
+            fault => fault,
+        }
+    }
+
     /// Measure the main loop runtime.
     pub fn meas_main_runtime(&self, m: &MainCtx<'_>) {
         let now = timer_get_large();
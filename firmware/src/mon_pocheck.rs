@@ -74,10 +74,30 @@ impl PoCheck {
         self.next_transition.set(m, now + DUR_PRE);
     }
 
-    pub fn run(&self, m: &MainCtx<'_>, speedo_hz: Option<MotorSpeed>) -> PoState {
+    pub fn run(
+        &self,
+        m: &MainCtx<'_>,
+        speedo_hz: Option<MotorSpeed>,
+        ext_enabled: bool,
+    ) -> PoState {
         let mut state = self.state.get(m);
 
         match state {
+            PoState::CheckIdle | PoState::CheckSecondaryShutoff | PoState::CheckPrimaryShutoff
+                if !ext_enabled =>
+            {
+                // The external enable input overrides both shutoff paths.
+                // The checks are meaningless. Restart them.
+                let now = timer_get_large();
+                state = PoState::CheckIdle;
+                self.part.set(m, PoStatePart::Pre);
+                self.next_transition.set(m, now + DUR_PRE);
+
+                // RPM must be zero anyway.
+                if self.is_error_condition(state, speedo_hz) {
+                    state = PoState::Error;
+                }
+            }
             PoState::CheckIdle | PoState::CheckSecondaryShutoff | PoState::CheckPrimaryShutoff => {
                 // Transition to the next state part?
                 let now = timer_get_large();
@@ -139,6 +159,10 @@ impl PoCheck {
         }
     }
 
+    pub fn get_state(&self, m: &MainCtx<'_>) -> PoState {
+        self.state.get(m)
+    }
+
     pub fn get_triac_phi_offs_ms(&self, m: &MainCtx<'_>) -> Option<Q7p8> {
         if cfg!(feature = "monitoring") {
             match self.state.get(m) {
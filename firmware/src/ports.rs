@@ -153,7 +153,7 @@ pub fn setup(c: &InitCtx) {
             w.bits(
                 pin_floating(0) | // setpoint, single ended ADC
                 pin_floating(1) | // vsense
-                pin_low(2) | // DNC
+                pin_pullup(2) | // ext enable, active low
                 pin_floating(3) | // AREF
                 pin_low(4) | // n_shutoff
                 pin_floating(5) | // motor temperature, single ended ADC
@@ -165,7 +165,7 @@ pub fn setup(c: &InitCtx) {
             w.bits(
                 pin_input(0) | // setpoint, single ended ADC
                 pin_input(1) | // vsense
-                pin_output(2) | // DNC
+                pin_input(2) | // ext enable, active low
                 pin_input(3) | // AREF
                 pin_output(4) | // n_shutoff
                 pin_input(5) | // motor temperature, single ended ADC
@@ -0,0 +1,51 @@
+// -*- coding: utf-8 -*-
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright (C) 2025 - 2026 Michael Büsch <m@bues.ch>
+
+//! Report the build-time calibration profile over the debug link.
+//!
+//! One byte is reported per complete set of debug values as `(index << 8) | byte`:
+//!
+//! - index 0, 1: Profile hash low and high byte.
+//! - index 2..: Profile name, terminated by a zero byte.
+
+use crate::{
+    calibration::profile::{PROFILE_HASH, PROFILE_NAME},
+    debug::Debug,
+};
+use avr_context::{MainCtx, MainCtxCell};
+
+/// Index of the terminating zero byte.
+const LAST_INDEX: u8 = PROFILE_NAME.len() as u8 + 2;
+
+pub struct ProfileInfo {
+    index: MainCtxCell<u8>,
+}
+
+impl ProfileInfo {
+    pub const fn new() -> Self {
+        Self {
+            index: MainCtxCell::new(0),
+        }
+    }
+
+    /// Report the next byte, if the previous one has been sent.
+    pub fn run(&self, m: &MainCtx<'_>, debug_round: bool) {
+        if !debug_round {
+            return;
+        }
+
+        let index = self.index.get(m);
+        let data = match index {
+            0 => PROFILE_HASH as u8,
+            1 => (PROFILE_HASH >> 8) as u8,
+            i => PROFILE_NAME.get(i as usize - 2).copied().unwrap_or(0),
+        };
+        Debug::Profile.log_u16(((index as u16) << 8) | data as u16);
+
+        self.index
+            .set(m, if index >= LAST_INDEX { 0 } else { index + 1 });
+    }
+}
+
+// vim: ts=4 sw=4 expandtab
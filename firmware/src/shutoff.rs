@@ -3,8 +3,12 @@
 // Copyright (C) 2025 - 2026 Michael Büsch <m@bues.ch>
 
 use crate::ports::{PORTA, PortOps as _};
+use avr_atomic::AvrAtomic;
 use avr_context::with_cs;
 
+/// The last commanded secondary shutoff path state. true = running.
+static SECONDARY_N_SHUTOFF: AvrAtomic<bool> = AvrAtomic::new();
+
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub enum Shutoff {
     MachineShutoff = 0,
@@ -32,12 +36,24 @@ impl core::ops::BitOrAssign for Shutoff {
 }
 
 /// Secondary shutoff path.
+#[inline(never)]
 pub fn set_secondary_shutoff(state: Shutoff) {
     let n_shutoff = match state {
         Shutoff::MachineShutoff => false,
         Shutoff::MachineRunning => true,
     };
+    SECONDARY_N_SHUTOFF.store(n_shutoff);
     with_cs(|cs| PORTA.set(cs, 4, n_shutoff));
 }
 
+/// Read back the secondary shutoff path pin
+/// and check whether it matches the commanded state.
+///
+/// The hardware has no dedicated feedback input for the secondary shutoff path.
+/// Therefore, only the pin level can be checked.
+pub fn secondary_shutoff_readback_ok() -> bool {
+    let n_shutoff = with_cs(|cs| PORTA.get(cs, 4));
+    n_shutoff == SECONDARY_N_SHUTOFF.load()
+}
+
 // vim: ts=4 sw=4 expandtab
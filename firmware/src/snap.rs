@@ -19,6 +19,7 @@ impl<T: Copy> Snap<T> {
 
 impl<T: Copy + PartialOrd + Add<Output = T> + Sub<Output = T>> Snap<T> {
     #[allow(clippy::if_same_then_else)]
+    #[inline(never)]
     pub fn update(&self, m: &MainCtx<'_>, min: T, max: T, hyst: T, new: T) -> T {
         let mut snapped = self.snapped.get(m);
 
@@ -4,9 +4,13 @@
 
 use crate::{
     analog::ac_capture_get,
-    calibration::speedo::{FILTER_SHIFT, OK_THRES, SPEEDO_FACT, SPEEDO_LOWLEVEL_TIMEOUT},
+    calibration::speedo::{
+        FILTER_SHIFT, MISSED_EDGE_HI, MISSED_EDGE_LO, OK_THRES, PERIOD_MODE_THRES, PLAUSIBLE_HI,
+        PLAUSIBLE_LO, REJECT_RESYNC_THRES, SPEEDO_FACT, SPEEDO_LOWLEVEL_TIMEOUT, SPEEDO_WINDOW,
+        SPEEDO_WINDOW_MODE, WINDOW_MODE_THRES,
+    },
     debug::Debug,
-    filter::FilterI16,
+    filter::{FilterI16, Median3I16},
     freq::Freq,
     timer::{LargeTimestamp, RelLargeTimestamp, TIMER_TICK_US, timer_get_large},
 };
@@ -42,10 +46,50 @@ impl MotorSpeed {
     }
 }
 
+/// Classification of a new edge duration relative to the filtered duration.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum EdgeClass {
+    /// The duration is plausible.
+    Plausible,
+    /// The duration spans two edges. One edge was missed.
+    MissedEdge,
+    /// The duration is too short. Spurious noise edge.
+    TooShort,
+    /// The duration is too long.
+    TooLong,
+}
+
+/// Speedometer measurement mode.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum MeasMode {
+    /// High speed: Measure the duration between consecutive edges.
+    Period,
+    /// Low speed: Count edges over a fixed time window.
+    Window,
+}
+
+impl MeasMode {
+    /// Mode after a reset of the measurement.
+    const INITIAL: Self = if SPEEDO_WINDOW_MODE {
+        Self::Window
+    } else {
+        Self::Period
+    };
+}
+
 pub struct Speedo {
     ok_count: MainCtxCell<u8>,
     prev_stamp: MainCtxCell<LargeTimestamp>,
+    mode: MainCtxCell<MeasMode>,
+    dur_valid: MainCtxCell<bool>,
+    win_first: MainCtxCell<LargeTimestamp>,
+    win_last: MainCtxCell<LargeTimestamp>,
+    win_periods: MainCtxCell<u8>,
+    dur_median: Median3I16,
     dur_filter: FilterI16,
+    reject_count: MainCtxCell<u8>,
+    rejected_edges: MainCtxCell<u8>,
+    missed_edges: MainCtxCell<u8>,
 }
 
 impl Speedo {
@@ -53,16 +97,35 @@ impl Speedo {
         Self {
             ok_count: MainCtxCell::new(0),
             prev_stamp: MainCtxCell::new(LargeTimestamp::new()),
+            mode: MainCtxCell::new(MeasMode::Period),
+            dur_valid: MainCtxCell::new(false),
+            win_first: MainCtxCell::new(LargeTimestamp::new()),
+            win_last: MainCtxCell::new(LargeTimestamp::new()),
+            win_periods: MainCtxCell::new(0),
+            dur_median: Median3I16::new(),
             dur_filter: FilterI16::new(),
+            reject_count: MainCtxCell::new(0),
+            rejected_edges: MainCtxCell::new(0),
+            missed_edges: MainCtxCell::new(0),
         }
     }
 
     pub fn init(&self, m: &MainCtx<'_>, now: LargeTimestamp) {
         self.prev_stamp.set(m, now);
+        self.mode.set(m, MeasMode::INITIAL);
+    }
+
+    /// Get the current measurement mode.
+    fn mode(&self, m: &MainCtx<'_>) -> MeasMode {
+        if SPEEDO_WINDOW_MODE {
+            self.mode.get(m)
+        } else {
+            MeasMode::Period
+        }
     }
 
     fn get_speed(&self, m: &MainCtx<'_>) -> Option<MotorSpeed> {
-        if self.ok_count.get(m) >= OK_THRES {
+        if self.ok_count.get(m) >= OK_THRES && self.dur_valid.get(m) {
             Some(MotorSpeed::from_period_dur(self.get_dur(m)))
         } else {
             None
@@ -73,22 +136,173 @@ impl Speedo {
         self.dur_filter.get(m).into()
     }
 
+    fn classify_duration(&self, m: &MainCtx<'_>, dur: RelLargeTimestamp) -> EdgeClass {
+        // The windows are in units of 1/16 of the filtered duration.
+        let dur = i32::from(i16::from(dur)) * 16;
+        let reference = i32::from(self.dur_filter.get(m));
+
+        if dur < reference * i32::from(PLAUSIBLE_LO) {
+            EdgeClass::TooShort
+        } else if dur <= reference * i32::from(PLAUSIBLE_HI) {
+            EdgeClass::Plausible
+        } else if dur >= reference * i32::from(MISSED_EDGE_LO)
+            && dur <= reference * i32::from(MISSED_EDGE_HI)
+        {
+            EdgeClass::MissedEdge
+        } else {
+            EdgeClass::TooLong
+        }
+    }
+
     fn new_duration(&self, m: &MainCtx<'_>, dur: RelLargeTimestamp) {
+        let dur: i16 = dur.into();
+
         // First real duration?
         if self.ok_count.get(m) <= 1 {
             // Just store.
-            self.dur_filter.set(m, dur.into(), FILTER_SHIFT);
+            self.dur_median.set(m, dur);
+            self.dur_filter.set(m, dur, FILTER_SHIFT);
         } else {
             // Filter duration.
-            self.dur_filter.run(m, dur.into(), FILTER_SHIFT);
+            let dur = self.dur_median.run(m, dur);
+            self.dur_filter.run(m, dur, FILTER_SHIFT);
         }
+        self.dur_valid.set(m, true);
         self.inc_ok(m);
     }
 
+    #[inline(never)]
+    fn reject_edge(&self, m: &MainCtx<'_>) {
+        self.rejected_edges
+            .set(m, self.rejected_edges.get(m).wrapping_add(1));
+        self.reject_count
+            .set(m, self.reject_count.get(m).saturating_add(1));
+    }
+
     fn inc_ok(&self, m: &MainCtx<'_>) {
         self.ok_count.set(m, self.ok_count.get(m).saturating_add(1));
     }
 
+    /// Handle a new edge in `MeasMode::Period`.
+    /// Returns false, if the edge shall be ignored.
+    fn new_period_edge(&self, m: &MainCtx<'_>, dur: RelLargeTimestamp) -> bool {
+        // Is the duration plausible compared to the filtered duration?
+        // The first real duration can't be checked.
+        let class = if self.ok_count.get(m) <= 1 {
+            EdgeClass::Plausible
+        } else {
+            self.classify_duration(m, dur)
+        };
+
+        match class {
+            EdgeClass::Plausible => {
+                self.reject_count.set(m, 0);
+                self.new_duration(m, dur);
+            }
+            EdgeClass::MissedEdge => {
+                // Compensate the missed edge by using the average duration.
+                self.reject_count.set(m, 0);
+                self.missed_edges
+                    .set(m, self.missed_edges.get(m).wrapping_add(1));
+                self.new_duration(m, dur.div(2));
+            }
+            EdgeClass::TooShort => {
+                // Spurious edge.
+                self.reject_edge(m);
+                if self.reject_count.get(m) < REJECT_RESYNC_THRES {
+                    // Ignore it and keep measuring from prev_stamp.
+                    return false;
+                }
+                // Too many rejects in a row. The speed really changed. Re-sync.
+                self.reject_count.set(m, 0);
+                self.ok_count.set(m, 1);
+            }
+            EdgeClass::TooLong => {
+                // Edges were lost or the motor slowed down rapidly. Re-sync.
+                self.reject_edge(m);
+                self.reject_count.set(m, 0);
+                self.ok_count.set(m, 1);
+            }
+        }
+        true
+    }
+
+    /// Start a new measurement window at the edge `stamp`.
+    fn window_start(&self, m: &MainCtx<'_>, stamp: LargeTimestamp) {
+        self.win_first.set(m, stamp);
+        self.win_last.set(m, stamp);
+        self.win_periods.set(m, 0);
+    }
+
+    /// Handle a new edge in `MeasMode::Window`.
+    fn new_window_edge(&self, m: &MainCtx<'_>, stamp: LargeTimestamp) {
+        self.win_last.set(m, stamp);
+        self.win_periods
+            .set(m, self.win_periods.get(m).saturating_add(1));
+        self.inc_ok(m);
+    }
+
+    /// Close the measurement window, if it has elapsed.
+    fn window_close(&self, m: &MainCtx<'_>, now: LargeTimestamp) {
+        let periods = self.win_periods.get(m);
+        if now >= self.win_first.get(m) + SPEEDO_WINDOW && periods > 0 {
+            // Average duration between the first and the last edge in the window.
+            let last = self.win_last.get(m);
+            let span: i16 = (last - self.win_first.get(m)).into();
+            let dur = span / periods as i16;
+
+            // Is the window average plausible compared to the previous window?
+            // Missed or spurious edges change the edge count of the window.
+            if self.dur_valid.get(m)
+                && self.classify_duration(m, dur.into()) != EdgeClass::Plausible
+            {
+                self.reject_edge(m);
+                if self.reject_count.get(m) >= REJECT_RESYNC_THRES {
+                    // Too many rejects in a row. The speed really changed. Re-sync.
+                    self.reject_count.set(m, 0);
+                    self.dur_valid.set(m, false);
+                }
+            } else {
+                self.reject_count.set(m, 0);
+                self.dur_median.set(m, dur);
+                self.dur_filter.set(m, dur, FILTER_SHIFT);
+                self.dur_valid.set(m, true);
+            }
+
+            // The last edge of this window is the first edge of the next window.
+            self.window_start(m, last);
+        }
+    }
+
+    /// Switch between `MeasMode::Window` and `MeasMode::Period`.
+    fn update_mode(&self, m: &MainCtx<'_>, prev_stamp: LargeTimestamp) {
+        if !SPEEDO_WINDOW_MODE || !self.dur_valid.get(m) {
+            return;
+        }
+        let freq = MotorSpeed::from_period_dur(self.get_dur(m)).as_freq();
+        match self.mode.get(m) {
+            MeasMode::Window => {
+                if freq >= PERIOD_MODE_THRES {
+                    self.mode.set(m, MeasMode::Period);
+                }
+            }
+            MeasMode::Period => {
+                if freq < WINDOW_MODE_THRES {
+                    self.mode.set(m, MeasMode::Window);
+                    self.window_start(m, prev_stamp);
+                }
+            }
+        }
+    }
+
+    /// Invalidate the measurement and restart from scratch.
+    #[inline(never)]
+    fn reset_ok(&self, m: &MainCtx<'_>) {
+        self.ok_count.set(m, 0);
+        self.dur_valid.set(m, false);
+        self.mode.set(m, MeasMode::INITIAL);
+    }
+
     pub fn run(&self, m: &MainCtx<'_>) -> Option<MotorSpeed> {
         let mut prev_stamp = self.prev_stamp.get(m);
 
@@ -98,28 +312,48 @@ impl Speedo {
             if self.ok_count.get(m) == 0 {
                 // first edge, just store prev_stamp and increment ok_count.
                 self.inc_ok(m);
+                if SPEEDO_WINDOW_MODE {
+                    self.window_start(m, ac);
+                }
             } else {
                 // ac stamp is valid?
                 if ac >= prev_stamp {
-                    let dur = ac - prev_stamp;
-                    self.new_duration(m, dur);
+                    match self.mode(m) {
+                        MeasMode::Period => {
+                            if !self.new_period_edge(m, ac - prev_stamp) {
+                                continue;
+                            }
+                        }
+                        MeasMode::Window => {
+                            self.new_window_edge(m, ac);
+                        }
+                    }
                 } else {
                     // invalid stamp.
-                    self.ok_count.set(m, 0);
+                    self.reset_ok(m);
                 }
             }
             prev_stamp = ac;
         }
 
-        // Check if prev_stamp is too old.
         let now = timer_get_large();
+
+        // Evaluate the low speed measurement window.
+        if self.mode(m) == MeasMode::Window {
+            self.window_close(m, now);
+        }
+        self.update_mode(m, prev_stamp);
+
+        // Check if prev_stamp is too old.
         if now - prev_stamp >= SPEEDO_LOWLEVEL_TIMEOUT {
-            self.ok_count.set(m, 0);
+            self.reset_ok(m);
         }
 
         self.prev_stamp.set(m, prev_stamp);
 
         Debug::SpeedoStatus.log_u16(self.ok_count.get(m) as u16);
+        Debug::SpeedoEdges
+            .log_u16(((self.missed_edges.get(m) as u16) << 8) | self.rejected_edges.get(m) as u16);
 
         self.get_speed(m)
     }
@@ -0,0 +1,128 @@
+// -*- coding: utf-8 -*-
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright (C) 2025 - 2026 Michael Büsch <m@bues.ch>
+
+//! Status LED blink codes on PB6.
+//!
+//! A blink code consists of long pulses followed by short pulses and a pause.
+//! The number of long pulses selects the category
+//! and the number of short pulses selects the value:
+//!
+//! - LED permanently on: Running.
+//! - 0 long, 1 short: Startup.
+//! - 0 long, 2 short: Syncing.
+//! - 1 long, n short: Power-on-check stage n.
+//! - 2 long, n short: Fault code n (see `MonFault`).
+
+use avr_context::MainCtx;
+
+#[cfg(feature = "status-led")]
+use crate::{
+    calibration::status_led::{STATUS_LED_PAUSE, STATUS_LED_STEP},
+    ports::{PORTB, PortOps as _},
+    timer::{LargeTimestamp, timer_get_large},
+};
+#[cfg(feature = "status-led")]
+use avr_context::{MainCtxCell, with_cs};
+
+/// PORTB bit of the status LED.
+#[cfg(feature = "status-led")]
+const PORTB_BIT: usize = 6;
+
+/// Number of steps of one long pulse, including the off time.
+#[cfg(feature = "status-led")]
+const LONG_STEPS: u8 = 4;
+/// Number of steps of one short pulse, including the off time.
+#[cfg(feature = "status-led")]
+const SHORT_STEPS: u8 = 2;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum BlinkCode {
+    /// LED permanently on.
+    On,
+    /// Number of long pulses followed by a number of short pulses.
+    Pulses { long: u8, short: u8 },
+}
+
+#[cfg(feature = "status-led")]
+impl BlinkCode {
+    /// Get the LED state for the step of the pattern.
+    /// Returns None, if the pattern is complete.
+    fn led(&self, step: u8) -> Option<bool> {
+        match *self {
+            BlinkCode::On => (step == 0).then_some(true),
+            BlinkCode::Pulses { long, short } => {
+                let long_steps = long * LONG_STEPS;
+                if step < long_steps {
+                    return Some(step % LONG_STEPS < LONG_STEPS - 1);
+                }
+                let step = step - long_steps;
+                let short_steps = short * SHORT_STEPS;
+                if step < short_steps {
+                    return Some(step % SHORT_STEPS < SHORT_STEPS - 1);
+                }
+                (step - short_steps < STATUS_LED_PAUSE).then_some(false)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "status-led")]
+pub struct StatusLed {
+    code: MainCtxCell<BlinkCode>,
+    step: MainCtxCell<u8>,
+    next_step: MainCtxCell<LargeTimestamp>,
+}
+
+#[cfg(feature = "status-led")]
+impl StatusLed {
+    pub const fn new() -> Self {
+        Self {
+            code: MainCtxCell::new(BlinkCode::On),
+            step: MainCtxCell::new(0),
+            next_step: MainCtxCell::new(LargeTimestamp::new()),
+        }
+    }
+
+    /// Drive the status LED.
+    ///
+    /// A new `code` is only taken over after the current pattern is complete.
+    pub fn run(&self, m: &MainCtx<'_>, code: BlinkCode) {
+        let now = timer_get_large();
+        if now < self.next_step.get(m) {
+            return;
+        }
+        self.next_step.set(m, now + STATUS_LED_STEP);
+
+        let mut step = self.step.get(m);
+        if step == 0 {
+            self.code.set(m, code);
+        }
+        let on = if let Some(on) = self.code.get(m).led(step) {
+            on
+        } else {
+            // Pattern complete. Start the new pattern.
+            step = 0;
+            self.code.set(m, code);
+            code.led(step).unwrap_or(false)
+        };
+        self.step.set(m, step + 1);
+
+        with_cs(|cs| PORTB.set(cs, PORTB_BIT, on));
+    }
+}
+
+/// No status LED is connected.
+#[cfg(not(feature = "status-led"))]
+pub struct StatusLed;
+
+#[cfg(not(feature = "status-led"))]
+impl StatusLed {
+    pub const fn new() -> Self {
+        Self
+    }
+
+    pub fn run(&self, _m: &MainCtx<'_>, _code: BlinkCode) {}
+}
+
+// vim: ts=4 sw=4 expandtab
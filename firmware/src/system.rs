@@ -5,6 +5,7 @@
 use crate::{
     analog::{Ac, Adc, AdcChannel},
     calibration::{
+        open_loop::{OPEN_LOOP, OPEN_LOOP_CURVE, OPEN_LOOP_Y_MAX},
         rpm_pid::{RPMPID_ILIM_NEG, RPMPID_ILIM_POS, RPMPID_PARAMS, RPMPID_PARAMS_SYNCING},
         setpoint::{SP_MIN_CUTOFF, SP_STEPS, SP_SYNC_THRES},
         speedo::{
@@ -12,25 +13,28 @@ use crate::{
         },
         system::{MAX_RPM, MOT_SOFT_LIMIT, STARTUP_DELAY},
     },
-    debug::Debug,
+    debug::{self, Debug},
+    extenable::ExtEnable,
     filter::Filter,
     freq::Freq,
     hw::mcu,
     mains::{MAINS_QUARTERWAVE_DUR, Mains, PhaseUpdate},
-    mon::Mon,
+    mon::{Mon, MonFault, MonInput},
     mon_pocheck::{PoCheck, PoState},
     pid::{Pid, PidIlim},
+    profile::ProfileInfo,
     shutoff::{Shutoff, set_secondary_shutoff},
     snap::Snap,
     speedo::{MotorSpeed, Speedo},
+    statusled::{BlinkCode, StatusLed},
     temp::{Temp, TempAdc},
-    timer::{LargeTimestamp, timer_get_large},
-    triac::Triac,
+    timer::{LargeTimestamp, RelLargeTimestamp, timer_get_large},
+    triac::{BURST_DUTY_MAX, BURST_MODE, Triac},
 };
-use avr_context::{MainCtx, MainCtxCell};
+use avr_context::{InitCtx, MainCtx, MainCtxCell};
 use avr_q::{Q7p8, q7p8, q15p8};
 
-#[cfg(feature = "debug")]
+#[cfg(all(feature = "debug", not(feature = "status-led")))]
 use crate::ports::{PORTB, PortOps as _};
 
 macro_rules! rpm {
@@ -76,9 +80,36 @@ fn f_to_trig_offs(f: Freq) -> Q7p8 {
     (fmax - f) * q7p8!(const 10 / MAX_FREQ)
 }
 
+/// Clamp negative frequency to 0.
+/// Convert 0..x Freq into 0..`BURST_DUTY_MAX` burst duty.
+fn f_to_burst_duty(f: Freq) -> u8 {
+    let fmax = rpm!(MAX_RPM).0;
+    let f = f.0.max(Q7p8::from_int(0)).min(fmax);
+
+    ((f.to_q() as i32 * BURST_DUTY_MAX as i32) / fmax.to_q() as i32) as u8
+}
+
+/// `Debug::OpMode` flag: Open-loop (manual phase angle) mode.
+const OP_MODE_OPEN_LOOP: u8 = 1 << 0;
+/// `Debug::OpMode` flag: Full-wave burst triac control.
+const OP_MODE_BURST: u8 = 1 << 1;
+
+/// Get the `Debug::OpMode` flags of the build.
+fn op_mode() -> u8 {
+    let mut mode = 0;
+    if OPEN_LOOP {
+        mode |= OP_MODE_OPEN_LOOP;
+    }
+    if BURST_MODE {
+        mode |= OP_MODE_BURST;
+    }
+    mode
+}
+
 /// Toggle the debug pin.
+/// The pin is not toggled, if it is used by the status LED.
 pub fn debug_toggle() {
-    #[cfg(feature = "debug")]
+    #[cfg(all(feature = "debug", not(feature = "status-led")))]
     avr_context::with_cs(|cs| PORTB.toggle(cs, 6));
 }
 
@@ -97,19 +128,25 @@ enum SysState {
 pub struct System {
     startup_delay_timeout: MainCtxCell<LargeTimestamp>,
     state: MainCtxCell<SysState>,
+    ext_enable: ExtEnable,
     mon: Mon,
     mon_pocheck: PoCheck,
     ac: Ac,
     adc: Adc,
     setpoint_snap: Snap<Freq>,
+    zero_passed: MainCtxCell<bool>,
     speedo: Speedo,
     speed_filter: [Filter; 2],
     prev_valid_speed: MainCtxCell<LargeTimestamp>,
     temp: Temp,
     mains: Mains,
     rpm_pid: Pid,
+    pid_y: MainCtxCell<Freq>,
+    conduction_high: MainCtxCell<bool>,
     mains_90deg_done: MainCtxCell<bool>,
     triac: Triac,
+    status_led: StatusLed,
+    profile_info: ProfileInfo,
 }
 
 impl System {
@@ -117,31 +154,40 @@ impl System {
         Self {
             startup_delay_timeout: MainCtxCell::new(LargeTimestamp::new()),
             state: MainCtxCell::new(SysState::Startup),
+            ext_enable: ExtEnable::new(),
             mon: Mon::new(),
             mon_pocheck: PoCheck::new(),
             ac: Ac::new(),
             adc: Adc::new(),
             setpoint_snap: Snap::new(Freq(q7p8!(const 0))),
+            zero_passed: MainCtxCell::new(false),
             speedo: Speedo::new(),
             speed_filter: [Filter::new(), Filter::new()],
             prev_valid_speed: MainCtxCell::new(LargeTimestamp::new()),
             temp: Temp::new(),
             mains: Mains::new(),
             rpm_pid: Pid::new(),
+            pid_y: MainCtxCell::new(Freq(q7p8!(const 0))),
+            conduction_high: MainCtxCell::new(false),
             mains_90deg_done: MainCtxCell::new(false),
             triac: Triac::new(),
+            status_led: StatusLed::new(),
+            profile_info: ProfileInfo::new(),
         }
     }
 
     /// System initialization.
     #[allow(non_snake_case)]
-    pub fn init(&self, m: &MainCtx<'_>, ADC: &mcu::ADC, AC: &mcu::AC) {
+    pub fn init(&self, c: &InitCtx<'_>, ADC: &mcu::ADC, AC: &mcu::AC) {
+        let m = c.main_ctx();
+
         // Set all shutoff paths.
         set_secondary_shutoff(Shutoff::MachineShutoff);
         self.triac.set_phi_offs_shutoff(m);
 
         self.adc.init(m, ADC);
         self.ac.init(AC);
+        self.ext_enable.init(m);
 
         self.startup_delay_timeout
             .set(m, timer_get_large() + STARTUP_DELAY);
@@ -178,13 +224,14 @@ impl System {
     /// Run the power-on-check.
     fn run_pocheck(&self, m: &MainCtx<'_>, speed: Option<MotorSpeed>) -> Shutoff {
         // Run the power-on-check state machine.
-        match self.mon_pocheck.run(m, speed) {
+        let ext_enabled = self.ext_enable.is_enabled(m);
+        match self.mon_pocheck.run(m, speed, ext_enabled) {
             PoState::CheckIdle | PoState::CheckSecondaryShutoff | PoState::CheckPrimaryShutoff => {
                 // Power-on-check is still running.
 
                 // Get power-on-check triac offset override.
                 if let Some(phi_offs_ms) = self.mon_pocheck.get_triac_phi_offs_ms(m) {
-                    self.triac.set_phi_offs_ms(m, phi_offs_ms);
+                    self.triac.set_check_trigger(m, phi_offs_ms);
                 } else {
                     self.triac.set_phi_offs_shutoff(m);
                 }
@@ -194,6 +241,9 @@ impl System {
 
                 // Ensure triac is turned off.
                 self.triac.set_phi_offs_shutoff(m);
+
+                // Show the fault code.
+                self.mon.report_fault(m, MonFault::PoCheck);
             }
             PoState::DoneOk => {
                 // Power-on-check finished successfully.
@@ -208,23 +258,30 @@ impl System {
             }
         }
 
+        // An open external enable input shuts off both paths.
+        let ext_shutoff = self.ext_enable.get_shutoff(m);
+
         // Set the secondary shutoff according to what the power-on-check wants.
-        set_secondary_shutoff(self.mon_pocheck.get_secondary_shutoff(m));
+        set_secondary_shutoff(self.mon_pocheck.get_secondary_shutoff(m) | ext_shutoff);
 
         // Set the primary shutoff according to what the power-on-check wants.
-        self.mon_pocheck.get_triac_shutoff(m)
+        self.mon_pocheck.get_triac_shutoff(m) | ext_shutoff
     }
 
     /// The system is in normal state (Syncing or Running).
+    #[allow(clippy::needless_late_init)]
     fn run_normal(
         &self,
         m: &MainCtx<'_>,
         phase_update: PhaseUpdate,
-        speed: Option<MotorSpeed>,
+        raw_speed: Option<MotorSpeed>,
     ) -> Shutoff {
         let now = timer_get_large();
         let mut triac_shutoff = Shutoff::MachineRunning;
 
+        // The speed control ignores the speedo in open-loop mode.
+        let speed = raw_speed.filter(|_| !OPEN_LOOP);
+
         // Interpret and filter the motor speed.
         let speed_filt = if let Some(speed) = speed {
             // We are sync'd now. Leave sync state.
@@ -235,6 +292,9 @@ impl System {
             filt = self.speed_filter[0].run(m, speed.as_freq().0, SPEED_FILTER_DIV_1ST);
             filt = self.speed_filter[1].run(m, filt, SPEED_FILTER_DIV_2ND);
             Freq(filt)
+        } else if OPEN_LOOP {
+            // There is no speed measurement in open-loop mode.
+            rpm!(0)
         } else if self.state.get(m) == SysState::Running
             && now - self.prev_valid_speed.get(m) < NO_SPEED_TIMEOUT
         {
@@ -257,7 +317,7 @@ impl System {
         }
 
         // Convert the setpoint to frequency.
-        let setpoint = if let Some(setpoint) = self.adc.get_result(m, AdcChannel::Setpoint) {
+        let mut setpoint = if let Some(setpoint) = self.adc.get_result(m, AdcChannel::Setpoint) {
             self.setpoint_snap.update(
                 m,
                 rpm!(0),                  // min
@@ -269,6 +329,17 @@ impl System {
             rpm!(0)
         };
 
+        // Zero-position interlock.
+        // After power-on and after a shutoff the setpoint must pass through zero,
+        // before the motor is allowed to run.
+        if !self.zero_passed.get(m) {
+            if setpoint < SP_MIN_CUTOFF {
+                self.zero_passed.set(m, true);
+            } else {
+                setpoint = rpm!(0);
+            }
+        }
+
         // If the setpoint is below the minimum cutoff, turn the triac off.
         if setpoint < SP_MIN_CUTOFF {
             triac_shutoff = Shutoff::MachineShutoff;
@@ -300,54 +371,91 @@ impl System {
 
             if setpoint <= SP_SYNC_THRES {
                 self.state.set(m, SysState::Syncing);
+            } else if OPEN_LOOP {
+                // There is nothing to sync to in open-loop mode.
+                self.state.set(m, SysState::Running);
             }
 
-            // Run the RPM controller.
-            let pid_speed;
-            let pid_params;
-            let pid_reset_i;
-            match self.state.get(m) {
-                SysState::Startup | SysState::PoCheck | SysState::Syncing => {
-                    pid_speed = SYNC_SPEEDO_SUBSTITUTE.lin_inter(setpoint);
-                    pid_params = &RPMPID_PARAMS_SYNCING;
-                    pid_reset_i = true;
-                }
-                SysState::Running => {
-                    pid_speed = speed_filt;
-                    pid_params = &RPMPID_PARAMS;
-                    pid_reset_i = false;
+            let pid_y = if OPEN_LOOP {
+                // The setpoint directly selects the firing angle.
+                OPEN_LOOP_CURVE.lin_inter(setpoint)
+            } else {
+                // Run the RPM controller.
+                let pid_speed;
+                let pid_params;
+                let pid_reset_i;
+                match self.state.get(m) {
+                    SysState::Startup | SysState::PoCheck | SysState::Syncing => {
+                        pid_speed = SYNC_SPEEDO_SUBSTITUTE.lin_inter(setpoint);
+                        pid_params = &RPMPID_PARAMS_SYNCING;
+                        pid_reset_i = true;
+                    }
+                    SysState::Running => {
+                        pid_speed = speed_filt;
+                        pid_params = &RPMPID_PARAMS;
+                        pid_reset_i = false;
+                    }
                 }
-            }
-            let pid_y = Freq(self.rpm_pid.run(
-                m,
-                pid_params,
-                &PidIlim {
-                    pos: RPMPID_ILIM_POS.lin_inter(speed_filt.0),
-                    neg: RPMPID_ILIM_NEG.lin_inter(speed_filt.0),
-                },
-                setpoint.0,
-                pid_speed.0,
-                pid_reset_i,
-            ));
+                Freq(self.rpm_pid.run(
+                    m,
+                    pid_params,
+                    &PidIlim {
+                        pos: RPMPID_ILIM_POS.lin_inter(speed_filt.0),
+                        neg: RPMPID_ILIM_NEG.lin_inter(speed_filt.0),
+                    },
+                    setpoint.0,
+                    pid_speed.0,
+                    pid_reset_i,
+                ))
+            };
 
             Debug::Setpoint.log_fixpt(setpoint.0);
             Debug::Speedo.log_fixpt(speed_filt.0);
             Debug::PidY.log_fixpt(pid_y.0);
+            self.pid_y.set(m, pid_y);
+
+            if BURST_MODE {
+                self.triac.set_burst_duty(m, f_to_burst_duty(pid_y));
+            } else {
+                let phi_offs_ms = f_to_trig_offs(pid_y);
+                self.triac.set_phi_offs_ms(m, phi_offs_ms);
+            }
 
-            let phi_offs_ms = f_to_trig_offs(pid_y);
-            self.triac.set_phi_offs_ms(m, phi_offs_ms);
+            // Check the conduction that is actually commanded to the triac.
+            if OPEN_LOOP {
+                self.conduction_high.set(
+                    m,
+                    self.triac.conduction_exceeds(
+                        m,
+                        RelLargeTimestamp::from_millis_fixpt(f_to_trig_offs(OPEN_LOOP_Y_MAX)),
+                        f_to_burst_duty(OPEN_LOOP_Y_MAX),
+                    ),
+                );
+            }
         }
 
         // Temperature shutoff.
         let mut safety_shutoff = self.temp.get_shutoff(m);
 
+        // External enable input shutoff.
+        let ext_shutoff = self.ext_enable.get_shutoff(m);
+        safety_shutoff |= ext_shutoff;
+
         // Safety monitoring check.
         safety_shutoff |= self.mon.check(
             m,
-            setpoint,
-            speed_filt,
-            raw_speedo_signal_is_ok,
-            mains_90deg_trigger,
+            &MonInput {
+                setpoint,
+                speedo: speed_filt,
+                speedo_ok: raw_speedo_signal_is_ok,
+                pid_y: self.pid_y.get(m),
+                conduction_high: self.conduction_high.get(m),
+                primary_shutoff: triac_shutoff,
+                mains_90deg: mains_90deg_trigger,
+                adc_bandgap: self.adc.get_result(m, AdcChannel::Bandgap),
+                adc_gnd: self.adc.get_result(m, AdcChannel::Gnd),
+                ext_enable: ext_shutoff,
+            },
         );
 
         // Secondary shutoff path.
@@ -355,6 +463,8 @@ impl System {
             // Safety shutoff: Activate both shutoff paths.
             triac_shutoff = Shutoff::MachineShutoff;
             set_secondary_shutoff(Shutoff::MachineShutoff);
+            // Don't restart without passing through zero.
+            self.zero_passed.set(m, false);
         } else {
             // Normal operation.
             set_secondary_shutoff(Shutoff::MachineRunning);
@@ -363,10 +473,34 @@ impl System {
         triac_shutoff
     }
 
+    /// Get the status LED blink code for the current system state.
+    fn get_blink_code(&self, m: &MainCtx<'_>) -> BlinkCode {
+        let fault = self.mon.get_fault(m);
+        if fault != MonFault::None {
+            return BlinkCode::Pulses {
+                long: 2,
+                short: fault as u8,
+            };
+        }
+        match self.state.get(m) {
+            SysState::Startup => BlinkCode::Pulses { long: 0, short: 1 },
+            SysState::PoCheck => BlinkCode::Pulses {
+                long: 1,
+                short: self.mon_pocheck.get_state(m) as u8 + 1,
+            },
+            SysState::Syncing => BlinkCode::Pulses { long: 0, short: 2 },
+            SysState::Running => BlinkCode::On,
+        }
+    }
+
     /// Main loop.
     #[allow(non_snake_case)]
     pub fn run(&self, m: &MainCtx<'_>, ADC: &mcu::ADC) {
         self.mon.meas_main_runtime(m);
+        self.status_led.run(m, self.get_blink_code(m));
+        let debug_round = debug::run(m);
+        self.profile_info.run(m, debug_round);
+        Debug::OpMode.log_u8(op_mode());
 
         let state = self.state.get(m);
         if state == SysState::Startup {
@@ -380,8 +514,12 @@ impl System {
             self.adc.run(m, ADC);
 
             // Evaluate the speedo signal.
+            // The power-on-check uses the raw speedo, even in open-loop mode.
             let speed = self.speedo.run(m);
 
+            // Sample the external enable input.
+            self.ext_enable.run(m);
+
             let triac_shutoff = match state {
                 SysState::Startup => Shutoff::MachineShutoff,
                 SysState::PoCheck => self.run_pocheck(m, speed),
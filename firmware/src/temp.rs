@@ -44,6 +44,7 @@ fn mot_kohms_to_celsius_double(r2: Q7p8) -> Q7p8 {
 }
 
 /// Convert microcontroller temp ADC to degree double-Celsius.
+#[inline(never)]
 fn uc_adc_to_celsius_double(adc: u16) -> Q7p8 {
     let adc = adc as i16;
     UC_CURVE.lin_inter(q7p8!(adc / 8))
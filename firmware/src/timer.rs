@@ -261,9 +261,11 @@ macro_rules! impl_reltimestamp {
                 $rel(ticks)
             }
 
+            /// Panics, if the time does not fit.
+            /// This fails the build for constants.
             #[inline]
             pub const fn from_micros(us: i32) -> $rel {
-                $rel((us / TIMER_TICK_US as i32) as $reltype)
+                Self::from_ticks_checked(us / TIMER_TICK_US as i32)
             }
 
             #[inline]
@@ -271,9 +273,17 @@ macro_rules! impl_reltimestamp {
                 (self.0 as i32) * (TIMER_TICK_US as i32)
             }
 
+            /// Panics, if the time does not fit.
+            /// This fails the build for constants.
             #[inline]
             pub const fn from_millis(ms: i32) -> $rel {
-                $rel(((ms * 1000) / TIMER_TICK_US as i32) as $reltype)
+                Self::from_ticks_checked((ms * 1000) / TIMER_TICK_US as i32)
+            }
+
+            #[inline]
+            const fn from_ticks_checked(ticks: i32) -> $rel {
+                assert!(ticks >= <$reltype>::MIN as i32 && ticks <= <$reltype>::MAX as i32);
+                $rel(ticks as $reltype)
             }
 
             #[inline]
@@ -353,6 +363,7 @@ impl From<LargeTimestamp> for Timestamp {
 
 impl RelLargeTimestamp {
     /// Convert a millisecond value in Q7p8 fixed-point format to a relative large timestamp.
+    #[inline(never)]
     pub fn from_millis_fixpt(ms: Q7p8) -> RelLargeTimestamp {
         // We must convert `ms` milliseconds to a corresponding number of ticks.
         //
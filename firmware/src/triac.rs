@@ -2,6 +2,12 @@
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 // Copyright (C) 2025 - 2026 Michael Büsch <m@bues.ch>
 
+//! Triac trigger control.
+//!
+//! - Phase angle control: The triac fires at an offset after each mains zero crossing.
+//! - Full-wave burst control (feature `burst`): The triac fires at the zero crossing
+//!   for whole mains cycles. The on-cycles are evenly distributed (Bresenham).
+
 use crate::{
     mains::{MAINS_HALFWAVE_DUR, Phase, PhaseUpdate},
     ports::{PORTB, PortOps as _},
@@ -26,6 +32,17 @@ const HALF_PULSE_LEN: RelTimestamp = RelTimestamp::from_micros(64);
 const MAX_TRIG_OFFS: RelLargeTimestamp =
     MAINS_HALFWAVE_DUR.sub(RelLargeTimestamp::from_micros(150));
 
+/// Full-wave burst control is used instead of phase angle control.
+pub const BURST_MODE: bool = cfg!(feature = "burst");
+
+/// Burst duty for 100% on-cycles.
+pub const BURST_DUTY_MAX: u8 = u8::MAX;
+
+/// Trigger offset in burst mode.
+/// Relative to the halfwave start.
+/// The mains voltage must be high enough for the triac to latch.
+const BURST_TRIG_OFFS: RelLargeTimestamp = RelLargeTimestamp::from_micros(300);
+
 static TRIAC_TIMER_STATE: Mutex<Cell<TriacTimerState>> =
     Mutex::new(Cell::new(TriacTimerState::TrigSet));
 static TRIAC_TIMER_COUNT: Mutex<Cell<u8>> = Mutex::new(Cell::new(0));
@@ -43,6 +60,7 @@ fn triac_timer_do_arm(cs: CriticalSection<'_>, ts: Timestamp) {
 }
 
 /// Cancel the possibly pending triac timer.
+#[inline(never)]
 fn triac_timer_do_cancel(cs: CriticalSection<'_>) {
     fence(SeqCst);
     timer_interrupt_a_cancel(cs);
@@ -138,6 +156,9 @@ fn set_trigger(cs: CriticalSection<'_>, trigger: bool) {
 pub struct Triac {
     phi_offs: MainCtxCell<RelLargeTimestamp>,
     trigger_pending: MainCtxCell<bool>,
+    burst_duty: MainCtxCell<Option<u8>>,
+    burst_acc: MainCtxCell<u16>,
+    burst_on: MainCtxCell<bool>,
 }
 
 impl Triac {
@@ -145,12 +166,18 @@ impl Triac {
         Self {
             phi_offs: MainCtxCell::new(RelLargeTimestamp::new()),
             trigger_pending: MainCtxCell::new(false),
+            burst_duty: MainCtxCell::new(None),
+            burst_acc: MainCtxCell::new(0),
+            burst_on: MainCtxCell::new(false),
         }
     }
 
     /// Set the next triac trigger offset, in milliseconds.
     /// Relative to the mains zero crossing.
+    /// This selects phase angle control.
+    #[inline(never)]
     pub fn set_phi_offs_ms(&self, m: &MainCtx<'_>, ms: Q7p8) {
+        self.burst_duty.set(m, None);
         self.phi_offs
             .set(m, RelLargeTimestamp::from_millis_fixpt(ms));
     }
@@ -159,9 +186,61 @@ impl Triac {
     #[inline(never)]
     pub fn set_phi_offs_shutoff(&self, m: &MainCtx<'_>) {
         with_cs(triac_timer_cancel);
+        self.burst_duty.set(m, None);
         self.phi_offs.set(m, MAINS_HALFWAVE_DUR);
     }
 
+    /// Set the ratio of on-cycles to all cycles, in units of 1/`BURST_DUTY_MAX`.
+    /// This selects full-wave burst control.
+    pub fn set_burst_duty(&self, m: &MainCtx<'_>, duty: u8) {
+        self.burst_duty.set(m, Some(duty));
+    }
+
+    /// Get the burst duty, if full-wave burst control is selected.
+    fn burst_duty(&self, m: &MainCtx<'_>) -> Option<u8> {
+        if BURST_MODE {
+            self.burst_duty.get(m)
+        } else {
+            None
+        }
+    }
+
+    /// Check whether the commanded conduction exceeds a limit.
+    /// The limit is the earliest trigger offset `phi_offs_min` for phase angle control
+    /// and the highest burst duty `duty_max` for full-wave burst control.
+    pub fn conduction_exceeds(
+        &self,
+        m: &MainCtx<'_>,
+        phi_offs_min: RelLargeTimestamp,
+        duty_max: u8,
+    ) -> bool {
+        match self.burst_duty(m) {
+            Some(duty) => duty > duty_max,
+            None => self.phi_offs.get(m) < phi_offs_min,
+        }
+    }
+
+    /// Enable the triac for a shutoff path check.
+    /// Phase angle control fires at the (late) trigger offset `ms`.
+    /// Full-wave burst control fires at the zero crossing of every mains cycle,
+    /// because a phase angle would defeat the burst control.
+    pub fn set_check_trigger(&self, m: &MainCtx<'_>, ms: Q7p8) {
+        if BURST_MODE {
+            self.set_burst_duty(m, BURST_DUTY_MAX);
+        } else {
+            self.set_phi_offs_ms(m, ms);
+        }
+    }
+
+    /// Decide whether the full mains cycle starting now is an on-cycle.
+    fn burst_cycle(&self, m: &MainCtx<'_>, duty: u8) {
+        let acc = self.burst_acc.get(m) + duty as u16;
+        let on = acc >= BURST_DUTY_MAX as u16;
+        self.burst_acc
+            .set(m, if on { acc - BURST_DUTY_MAX as u16 } else { acc });
+        self.burst_on.set(m, on);
+    }
+
     /// Run the triac trigger timer arm logic.
     pub fn run(
         &self,
@@ -177,19 +256,34 @@ impl Triac {
             if phase == Phase::Notsync || shutoff == Shutoff::MachineShutoff {
                 triac_timer_cancel(cs);
                 self.trigger_pending.set(m, false);
+                self.burst_on.set(m, false);
                 return;
             }
 
+            let burst_duty = self.burst_duty(m);
+
             // Zero crossing detected?
             // If so, then we need to arm the next trigger timer soon.
             if phase_update == PhaseUpdate::Changed {
                 triac_timer_cancel(cs);
-                self.trigger_pending.set(m, true);
+                if let Some(duty) = burst_duty {
+                    // A full mains cycle starts with the positive halfwave.
+                    if phase == Phase::PosHalfwave {
+                        self.burst_cycle(m, duty);
+                    }
+                    self.trigger_pending.set(m, self.burst_on.get(m));
+                } else {
+                    self.trigger_pending.set(m, true);
+                }
             }
 
             // Check if we need to arm the next trigger timer.
             if self.trigger_pending.get(m) {
-                let trig_offs = self.phi_offs.get(m);
+                let trig_offs = if burst_duty.is_some() {
+                    BURST_TRIG_OFFS
+                } else {
+                    self.phi_offs.get(m)
+                };
                 if trig_offs <= MAX_TRIG_OFFS {
                     // Calculate the absolute trigger time.
                     let trig_time = phaseref + trig_offs;
@@ -0,0 +1,67 @@
+// -*- coding: utf-8 -*-
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright (C) 2025 - 2026 Michael Büsch <m@bues.ch>
+
+//! Windowed watchdog emulation.
+//!
+//! The hardware watchdog only catches main loops that are too slow.
+//! This additionally refuses to service the watchdog,
+//! if the main loop iterations are too fast.
+//! The lower bound is checked over `WDT_WINDOW_LOOPS` iterations,
+//! because one iteration can be shorter than one timer tick.
+
+use crate::{
+    calibration::system::{WDT_WINDOW_LOOPS, WDT_WINDOW_MAX, WDT_WINDOW_MIN},
+    reset_system,
+    timer::{LargeTimestamp, timer_get_large},
+};
+use avr_context::{MainCtx, MainCtxCell};
+use avr_device::asm::wdr;
+
+pub struct WdtWindow {
+    prev_service: MainCtxCell<LargeTimestamp>,
+    window_start: MainCtxCell<LargeTimestamp>,
+    count: MainCtxCell<u8>,
+}
+
+impl WdtWindow {
+    pub const fn new() -> Self {
+        Self {
+            prev_service: MainCtxCell::new(LargeTimestamp::new()),
+            window_start: MainCtxCell::new(LargeTimestamp::new()),
+            count: MainCtxCell::new(0),
+        }
+    }
+
+    pub fn init(&self, m: &MainCtx<'_>) {
+        let now = timer_get_large();
+        self.prev_service.set(m, now);
+        self.window_start.set(m, now);
+    }
+
+    /// Service the watchdog.
+    ///
+    /// Resets the system, if the time since the previous service is too long
+    /// or if the last `WDT_WINDOW_LOOPS` services were too fast.
+    pub fn service(&self, m: &MainCtx<'_>) {
+        let now = timer_get_large();
+        if now - self.prev_service.get(m) > WDT_WINDOW_MAX {
+            reset_system();
+        }
+        self.prev_service.set(m, now);
+
+        let count = self.count.get(m) + 1;
+        if count >= WDT_WINDOW_LOOPS {
+            if now - self.window_start.get(m) < WDT_WINDOW_MIN {
+                reset_system();
+            }
+            self.window_start.set(m, now);
+            self.count.set(m, 0);
+        } else {
+            self.count.set(m, count);
+        }
+        wdr();
+    }
+}
+
+// vim: ts=4 sw=4 expandtab
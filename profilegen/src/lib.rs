@@ -0,0 +1,428 @@
+// -*- coding: utf-8 -*-
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// Copyright (C) 2025 - 2026 Michael Büsch <m@bues.ch>
+
+//! Validate a calibration profile and generate the firmware profile module from it.
+//!
+//! This is used by the firmware `build.rs`.
+//! It lives in its own host crate, so that the validation can be tested with `cargo test`.
+
+#![forbid(unsafe_code)]
+
+use anyhow::{self as ah, Context as _, format_err as err};
+use crc16::crc16_update;
+use std::fmt::Write as _;
+use toml::{Table, Value};
+
+/// Maximum length of the profile name.
+const NAME_MAX_LEN: usize = 16;
+/// Highest RPM that fits into `Freq`.
+const RPM_MAX: i64 = 25500;
+/// Upper bound of the RPM PID gains.
+const GAIN_MAX: f64 = 16.0;
+/// Upper bound of the absolute RPM PID I-limits.
+const ILIM_MAX: f64 = 120.0;
+/// Number of I-limit curve points. Must match the firmware I-limit curves.
+const ILIM_POINTS: usize = 4;
+/// Temperature range in deg Celsius that fits into `celsius!`.
+const CELSIUS_MIN: i64 = -200;
+const CELSIUS_MAX: i64 = 250;
+
+/// Q7.8 value from the profile.
+/// Either an integer, a float or a `"numerator/denominator"` string.
+struct Fixpt {
+    value: f64,
+    code: String,
+}
+
+fn section<'a>(tab: &'a Table, name: &str) -> ah::Result<&'a Table> {
+    tab.get(name)
+        .and_then(Value::as_table)
+        .ok_or_else(|| err!("Missing section [{name}]"))
+}
+
+fn get<'a>(tab: &'a Table, sect: &str, key: &str) -> ah::Result<&'a Value> {
+    section(tab, sect)?
+        .get(key)
+        .ok_or_else(|| err!("Missing key '{key}' in [{sect}]"))
+}
+
+fn to_int(val: &Value, what: &str, lo: i64, hi: i64) -> ah::Result<i64> {
+    let int = val
+        .as_integer()
+        .ok_or_else(|| err!("{what}: Expected an integer"))?;
+    if !(lo..=hi).contains(&int) {
+        return Err(err!("{what}: {int} is out of range {lo}..={hi}"));
+    }
+    Ok(int)
+}
+
+fn to_fixpt(val: &Value, what: &str, lo: f64, hi: f64) -> ah::Result<Fixpt> {
+    let fixpt = match val {
+        Value::Integer(int) => {
+            let int = i8::try_from(*int).map_err(|_| err!("{what}: {int} is out of range"))?;
+            Fixpt {
+                value: int.into(),
+                code: format!("q7p8!(const {int})"),
+            }
+        }
+        Value::Float(f) => {
+            let q = (f * 256.0).round();
+            if !(i16::MIN.into()..=i16::MAX.into()).contains(&q) {
+                return Err(err!("{what}: {f} is out of range"));
+            }
+            Fixpt {
+                value: *f,
+                code: format!("Q7p8::from_q({})", q as i16),
+            }
+        }
+        Value::String(s) => {
+            let (num, den) = s
+                .split_once('/')
+                .ok_or_else(|| err!("{what}: Expected \"numerator/denominator\""))?;
+            let num: i16 = num.trim().parse().context(what.to_string())?;
+            let den: i16 = den.trim().parse().context(what.to_string())?;
+            if den <= 0 {
+                return Err(err!("{what}: Invalid denominator {den}"));
+            }
+            Fixpt {
+                value: f64::from(num) / f64::from(den),
+                code: format!("q7p8!(const {num} / {den})"),
+            }
+        }
+        _ => return Err(err!("{what}: Expected a number")),
+    };
+    if !(lo..=hi).contains(&fixpt.value) {
+        return Err(err!("{what}: {} is out of range {lo}..={hi}", fixpt.value));
+    }
+    Ok(fixpt)
+}
+
+fn to_points(val: &Value, what: &str) -> ah::Result<Vec<(Value, Value)>> {
+    let arr = val
+        .as_array()
+        .ok_or_else(|| err!("{what}: Expected an array of points"))?;
+    arr.iter()
+        .map(|p| match p.as_array().map(Vec::as_slice) {
+            Some([x, y]) => Ok((x.clone(), y.clone())),
+            _ => Err(err!("{what}: Expected [x, y] points")),
+        })
+        .collect()
+}
+
+/// Generate the I-limit curve points: `[rpm, I-limit]`.
+fn gen_ilim(val: &Value, what: &str, max_rpm: i64, neg: bool) -> ah::Result<String> {
+    let points = to_points(val, what)?;
+    if points.len() != ILIM_POINTS {
+        return Err(err!("{what}: Expected {ILIM_POINTS} points"));
+    }
+    let (lo, hi) = if neg {
+        (-ILIM_MAX, 0.0)
+    } else {
+        (0.0, ILIM_MAX)
+    };
+    let mut code = String::new();
+    let mut prev_rpm = -1;
+    for (x, y) in &points {
+        let rpm = to_int(x, what, 0, max_rpm)?;
+        if rpm <= prev_rpm {
+            return Err(err!("{what}: RPM points are not strictly increasing"));
+        }
+        prev_rpm = rpm;
+        let ilim = to_fixpt(y, what, lo, hi)?;
+        writeln!(code, "    (rpm!({rpm}).0, {}),", ilim.code)?;
+    }
+    Ok(code)
+}
+
+/// Generate the NTC curve points: `[kOhms, deg Celsius]`.
+fn gen_ntc(val: &Value, what: &str) -> ah::Result<(usize, String)> {
+    let points = to_points(val, what)?;
+    if points.len() < 2 {
+        return Err(err!("{what}: Expected at least 2 points"));
+    }
+    let mut code = String::new();
+    let mut prev: Option<(f64, i64)> = None;
+    for (x, y) in &points {
+        let kohms = to_fixpt(x, what, 0.0, 127.0)?;
+        let cel = to_int(y, what, CELSIUS_MIN, CELSIUS_MAX)?;
+        if let Some((prev_kohms, prev_cel)) = prev
+            && (kohms.value <= prev_kohms || cel >= prev_cel)
+        {
+            return Err(err!(
+                "{what}: kOhms must be strictly increasing and deg Celsius strictly decreasing"
+            ));
+        }
+        prev = Some((kohms.value, cel));
+        writeln!(code, "    ({}, celsius!({cel})),", kohms.code)?;
+    }
+    Ok((points.len(), code))
+}
+
+/// Validate the profile `text` and generate the Rust module code from it.
+pub fn generate(name: &str, text: &str) -> ah::Result<String> {
+    if name.is_empty()
+        || name.len() > NAME_MAX_LEN
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(err!(
+            "Profile name must be 1 to {NAME_MAX_LEN} characters of [A-Za-z0-9_-]"
+        ));
+    }
+    let tab: Table = text.parse().context("Parse profile")?;
+    let hash = text.bytes().fold(0xFFFF, crc16_update);
+
+    let max_rpm = to_int(get(&tab, "system", "max_rpm")?, "max_rpm", 1000, RPM_MAX)?;
+    let soft = to_int(
+        get(&tab, "system", "mot_soft_limit")?,
+        "mot_soft_limit",
+        0,
+        RPM_MAX,
+    )?;
+    let hard = to_int(
+        get(&tab, "system", "mot_hard_limit")?,
+        "mot_hard_limit",
+        0,
+        RPM_MAX,
+    )?;
+    if !(max_rpm < soft && soft < hard) {
+        return Err(err!(
+            "Expected max_rpm < mot_soft_limit < mot_hard_limit, got {max_rpm} / {soft} / {hard}"
+        ));
+    }
+
+    let speedo_fact = to_int(get(&tab, "speedo", "speedo_fact")?, "speedo_fact", 2, 16)?;
+
+    let kp = to_fixpt(get(&tab, "rpm_pid", "kp")?, "kp", 0.0, GAIN_MAX)?;
+    let ki = to_fixpt(get(&tab, "rpm_pid", "ki")?, "ki", 0.0, GAIN_MAX)?;
+    let kd = to_fixpt(get(&tab, "rpm_pid", "kd")?, "kd", 0.0, GAIN_MAX)?;
+    let ilim_neg = gen_ilim(get(&tab, "rpm_pid", "ilim_neg")?, "ilim_neg", max_rpm, true)?;
+    let ilim_pos = gen_ilim(
+        get(&tab, "rpm_pid", "ilim_pos")?,
+        "ilim_pos",
+        max_rpm,
+        false,
+    )?;
+
+    let temp_hi = to_int(
+        get(&tab, "temp", "temp_limit_hi")?,
+        "temp_limit_hi",
+        CELSIUS_MIN,
+        CELSIUS_MAX,
+    )?;
+    let temp_lo = to_int(
+        get(&tab, "temp", "temp_limit_lo")?,
+        "temp_limit_lo",
+        CELSIUS_MIN,
+        CELSIUS_MAX,
+    )?;
+    if temp_lo >= temp_hi {
+        return Err(err!(
+            "Expected temp_limit_lo < temp_limit_hi, got {temp_lo} / {temp_hi}"
+        ));
+    }
+    let (ntc_len, ntc_curve) = gen_ntc(get(&tab, "temp", "ntc_curve")?, "ntc_curve")?;
+
+    let mut code = String::new();
+    writeln!(code, "// Generated by build.rs from profile '{name}'.")?;
+    writeln!(code, "pub const PROFILE_NAME: &[u8] = b\"{name}\";")?;
+    writeln!(code, "pub const PROFILE_HASH: u16 = 0x{hash:04X};")?;
+    writeln!(code, "pub const MAX_RPM: i16 = {max_rpm};")?;
+    writeln!(code, "pub const MOT_SOFT_LIMIT: Freq = rpm!({soft});")?;
+    writeln!(code, "pub const MOT_HARD_LIMIT: Freq = rpm!({hard});")?;
+    writeln!(code, "pub const SPEEDO_FACT: u32 = {speedo_fact};")?;
+    writeln!(code, "pub const RPMPID_PARAMS: PidParams = PidParams {{")?;
+    writeln!(code, "    kp: {},", kp.code)?;
+    writeln!(code, "    ki: {},", ki.code)?;
+    writeln!(code, "    kd: {},", kd.code)?;
+    writeln!(code, "}};")?;
+    writeln!(
+        code,
+        "pub const RPMPID_ILIM_NEG: [(Q7p8, Q7p8); {ILIM_POINTS}] = [\n{ilim_neg}];"
+    )?;
+    writeln!(
+        code,
+        "pub const RPMPID_ILIM_POS: [(Q7p8, Q7p8); {ILIM_POINTS}] = [\n{ilim_pos}];"
+    )?;
+    writeln!(code, "pub const TEMP_LIMIT_HI: Q7p8 = celsius!({temp_hi});")?;
+    writeln!(code, "pub const TEMP_LIMIT_LO: Q7p8 = celsius!({temp_lo});")?;
+    writeln!(code, "pub const NTC_CURVE_LEN: usize = {ntc_len};")?;
+    writeln!(
+        code,
+        "pub const NTC_CURVE: [(Q7p8, Q7p8); NTC_CURVE_LEN] = [\n{ntc_curve}];"
+    )?;
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, path::PathBuf};
+
+    fn default_profile() -> String {
+        let path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../firmware/profiles/default.toml");
+        fs::read_to_string(path).unwrap()
+    }
+
+    /// Replace the line starting with `key =` in the default profile.
+    fn patched(key: &str, line: &str) -> String {
+        let text = default_profile();
+        let prefix = format!("{key} =");
+        assert!(text.lines().any(|l| l.starts_with(&prefix)));
+        text.lines()
+            .map(|l| if l.starts_with(&prefix) { line } else { l })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn gen_err(name: &str, text: &str) -> String {
+        format!("{:#}", generate(name, text).unwrap_err())
+    }
+
+    #[test]
+    fn test_all_profiles() {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../firmware/profiles");
+        let mut count = 0;
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|e| e == "toml") {
+                let name = path.file_stem().unwrap().to_str().unwrap();
+                let text = fs::read_to_string(&path).unwrap();
+                generate(name, &text).unwrap();
+                count += 1;
+            }
+        }
+        assert!(count > 0);
+    }
+
+    #[test]
+    fn test_default_code() {
+        let code = generate("default", &default_profile()).unwrap();
+        assert!(code.contains("pub const PROFILE_NAME: &[u8] = b\"default\";"));
+        assert!(code.contains("pub const MOT_SOFT_LIMIT: Freq = rpm!(24500);"));
+        assert!(code.contains("    kp: q7p8!(const 8 / 5),"));
+        assert!(code.contains("pub const NTC_CURVE_LEN: usize = 7;"));
+    }
+
+    #[test]
+    fn test_hash() {
+        let a = generate("default", &default_profile()).unwrap();
+        let b = generate("default", &patched("temp_limit_lo", "temp_limit_lo = 79")).unwrap();
+        let hash = |code: &str| {
+            code.lines()
+                .find(|l| l.starts_with("pub const PROFILE_HASH"))
+                .unwrap()
+                .to_string()
+        };
+        assert_ne!(hash(&a), hash(&b));
+    }
+
+    #[test]
+    fn test_name() {
+        let text = default_profile();
+        assert!(generate("", &text).is_err());
+        assert!(generate("a/b", &text).is_err());
+        assert!(generate("x".repeat(NAME_MAX_LEN + 1).as_str(), &text).is_err());
+        generate("x".repeat(NAME_MAX_LEN).as_str(), &text).unwrap();
+        generate("my-machine_2", &text).unwrap();
+    }
+
+    #[test]
+    fn test_rpm_limits() {
+        let e = gen_err("t", &patched("mot_soft_limit", "mot_soft_limit = 25500"));
+        assert!(e.contains("mot_soft_limit < mot_hard_limit"));
+        let e = gen_err("t", &patched("mot_soft_limit", "mot_soft_limit = 23000"));
+        assert!(e.contains("max_rpm < mot_soft_limit"));
+        let e = gen_err("t", &patched("mot_hard_limit", "mot_hard_limit = 25501"));
+        assert!(e.contains("out of range"));
+        let e = gen_err("t", &patched("speedo_fact", "speedo_fact = 1"));
+        assert!(e.contains("speedo_fact"));
+    }
+
+    #[test]
+    fn test_fixpt() {
+        generate("t", &patched("kp", "kp = 2")).unwrap();
+        generate("t", &patched("kp", "kp = 1.5")).unwrap();
+        let e = gen_err("t", &patched("kp", "kp = 17"));
+        assert!(e.contains("out of range"));
+        let e = gen_err("t", &patched("kp", "kp = \"1/0\""));
+        assert!(e.contains("Invalid denominator"));
+        let e = gen_err("t", &patched("kp", "kp = \"1\""));
+        assert!(e.contains("numerator/denominator"));
+        let e = gen_err("t", &patched("kp", "kp = \"-1/2\""));
+        assert!(e.contains("out of range"));
+    }
+
+    #[test]
+    fn test_ilim() {
+        let e = gen_err(
+            "t",
+            &patched(
+                "ilim_pos",
+                "ilim_pos = [ [0, 0], [300, 0], [300, 80], [24000, 80] ]",
+            ),
+        );
+        assert!(e.contains("strictly increasing"));
+        let e = gen_err(
+            "t",
+            &patched("ilim_pos", "ilim_pos = [ [0, 0], [24000, 80] ]"),
+        );
+        assert!(e.contains("Expected 4 points"));
+        let e = gen_err(
+            "t",
+            &patched(
+                "ilim_neg",
+                "ilim_neg = [ [0, 0], [300, 0], [301, 10], [24000, -10] ]",
+            ),
+        );
+        assert!(e.contains("out of range"));
+        let e = gen_err(
+            "t",
+            &patched(
+                "ilim_pos",
+                "ilim_pos = [ [0, 0], [300, 0], [301, 80], [24001, 80] ]",
+            ),
+        );
+        assert!(e.contains("out of range"));
+    }
+
+    #[test]
+    fn test_temp() {
+        let e = gen_err("t", &patched("temp_limit_lo", "temp_limit_lo = 100"));
+        assert!(e.contains("temp_limit_lo < temp_limit_hi"));
+        let e = gen_err("t", &patched("temp_limit_hi", "temp_limit_hi = 251"));
+        assert!(e.contains("out of range"));
+    }
+
+    #[test]
+    fn test_ntc() {
+        // The NTC curve is the last entry of the profile.
+        let text = default_profile();
+        let with_ntc = |curve: &str| {
+            let (head, _) = text.split_once("ntc_curve =").unwrap();
+            format!("{head}ntc_curve = {curve}\n")
+        };
+        let e = gen_err("t", &with_ntc("[ [1, 25] ]"));
+        assert!(e.contains("at least 2 points"));
+        let e = gen_err("t", &with_ntc("[ [1, 25], [1, 20] ]"));
+        assert!(e.contains("strictly increasing"));
+        let e = gen_err("t", &with_ntc("[ [1, 25], [2, 25] ]"));
+        assert!(e.contains("strictly decreasing"));
+        let e = gen_err("t", &with_ntc("[ [1, 25], [2, 20, 3] ]"));
+        assert!(e.contains("[x, y] points"));
+        generate("t", &with_ntc("[ [1, 25], [2, 20] ]")).unwrap();
+    }
+
+    #[test]
+    fn test_missing() {
+        let e = gen_err("t", &patched("kd", "# kd removed"));
+        assert!(e.contains("Missing key 'kd' in [rpm_pid]"));
+        let e = gen_err("t", &default_profile().replace("[speedo]", "[speed]"));
+        assert!(e.contains("speedo"));
+    }
+}
+
+// vim: ts=4 sw=4 expandtab